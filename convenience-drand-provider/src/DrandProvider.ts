@@ -10,6 +10,16 @@ interface PendingDrandBeacon {
     }>
  }
 
+/**
+ * Second report of the pendingdrandbeacon inspect, with the exact round the middleware waits
+ */
+interface PendingDrandBeaconReport {
+    pending_drand_beacon?: {
+        timestamp: number
+        round: number | null
+    }
+}
+
 export class DrandProvider {
 
     desiredState: 'RUNNING' | 'STOPPED' = 'RUNNING'
@@ -58,7 +68,8 @@ export class DrandProvider {
             if (Array.isArray(res.data.reports) && res.data.reports.length > 0) {
                 const firstReport = res.data.reports.at(0);
                 if (firstReport?.payload && firstReport.payload !== '0x00') {
-                    return { inputTime: Number(firstReport.payload) }
+                    const round = this.pendingRound(res.data.reports.at(1)?.payload)
+                    return { inputTime: Number(firstReport.payload), round }
                 }
             }
         } catch (error) {
@@ -76,6 +87,23 @@ export class DrandProvider {
         return null;
     }
 
+    /**
+     * The middleware only draws from the round due at the pending time, so send that one instead of the latest
+     */
+    private pendingRound(payload?: string) {
+        if (!payload) {
+            return undefined
+        }
+        try {
+            const json = Buffer.from(payload.replace(/^0x/, ''), 'hex').toString('utf-8')
+            const report: PendingDrandBeaconReport = JSON.parse(json)
+            return report.pending_drand_beacon?.round ?? undefined
+        } catch (error) {
+            console.error('Error decoding the pending drand beacon report', error)
+            return undefined
+        }
+    }
+
     private createDrandClient() {
         const options: ChainOptions = {
             chainVerificationParams: { chainHash: this.drandConfig.chainHash, publicKey: this.drandConfig.publicKey },
@@ -97,7 +125,7 @@ export class DrandProvider {
             try {
                 const pending = await this.pendingDrandBeacon()
                 if (this.canSendBeacon(pending)) {
-                    const beacon = await fetchBeacon(this.drandClient)
+                    const beacon = await fetchBeacon(this.drandClient, pending.round)
                     console.log('sending beacon', beacon.round)
                     this.inputSender.sendInput({ payload: JSON.stringify({ beacon }) })
                     this.lastPendingTime = pending.inputTime
@@ -16,6 +16,12 @@ describe('DrandProvider', () => {
             const resp = await provider.pendingDrandBeacon()
             expect(resp?.inputTime).toBeDefined()
         })
+        it("should inform the exact round the middleware waits", async () => {
+            Helper.nockInspectEndpointRoundIsNeeded(2832127)
+            const provider = new DrandProvider()
+            const resp = await provider.pendingDrandBeacon()
+            expect(resp?.round).toBe(2832127)
+        })
         it("should respond null when inspect response is 0x00, aka no need for beacon", async () => {
             Helper.nockInspectEndpointRandomIsntNeeded()
             const provider = new DrandProvider()
@@ -18,6 +18,23 @@ export default class Helper {
                 "processed_input_count": 0
             })
     }
+    static nockInspectEndpointRoundIsNeeded(round: number) {
+        const report = { pending_drand_beacon: { timestamp: 1, round } }
+        return nock(Helper.nockUrl)
+            .get(/\/inspect\/pendingdrandbeacon/)
+            .reply(200, {
+                "status": "Accepted",
+                "reports": [
+                    {
+                        "payload": "0x01"
+                    },
+                    {
+                        "payload": `0x${Buffer.from(JSON.stringify(report)).toString('hex')}`
+                    }
+                ],
+                "processed_input_count": 0
+            })
+    }
     static nockInspectEndpointRandomIsntNeeded() {
         return nock(Helper.nockUrl)
             .get(/\/inspect\/pendingdrandbeacon/)
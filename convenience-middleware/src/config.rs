@@ -0,0 +1,173 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use cartesi_rollup_metadata::Address;
+use clap::{Parser, ValueEnum};
+
+use crate::{
+    drand::validate_drand_chain,
+    errors::ConfigError,
+    models::structs::{DerivationScheme, DrandConfig, DrandEnv, ProvenanceMode},
+    utils::util::{
+        config_owner_from_env, derivation_scheme_from_env, drand_env_from_env,
+        provenance_mode_from_env, DRAND_CONFIG_PATH, JOURNAL_FILE_NAME,
+    },
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/**
+ * Flags of the middleware, a missing flag is read from its env var and then from the default
+ */
+#[derive(Parser, Debug)]
+#[command(version, about = "Drand randomness for Cartesi DApps")]
+pub struct Cli {
+    /**
+     * Address the HTTP server binds to
+     */
+    #[arg(long, env = "MIDDLEWARE_BIND_ADDRESS", default_value = "0.0.0.0")]
+    pub bind_address: String,
+
+    /**
+     * Port the HTTP server listens on
+     */
+    #[arg(long, env = "MIDDLEWARE_PORT", default_value_t = 8080)]
+    pub port: u16,
+
+    /**
+     * Drand config file, updated when the config changes at runtime
+     */
+    #[arg(long, env = "DRAND_CONFIG_PATH", default_value = DRAND_CONFIG_PATH)]
+    pub config_path: PathBuf,
+
+    /**
+     * Journal of the input buffer, drand.journal.json next to the drand config file when missing
+     */
+    #[arg(long, env = "DRAND_JOURNAL_PATH")]
+    pub journal_path: Option<PathBuf>,
+
+    #[arg(long, env = "MIDDLEWARE_LOG_FORMAT", value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /**
+     * Rollup HTTP server the inputs come from and the outputs go to
+     */
+    #[arg(long, env = "ROLLUP_HTTP_SERVER_URL")]
+    pub rollup_http_server_url: String,
+}
+
+/**
+ * Configuration of the middleware, built once at startup.
+ *
+ * The server settings come from the flags, then the env, then the defaults.
+ * The drand settings come from the drand config file, which keeps the updates
+ * received at runtime, and the values missing there from the env.
+ */
+#[derive(Debug)]
+pub struct Config {
+    pub bind_address: String,
+    pub port: u16,
+    pub config_path: PathBuf,
+    pub journal_path: PathBuf,
+    pub log_format: LogFormat,
+    pub rollup_url: String,
+    pub drand: DrandConfig,
+    pub provenance_mode: ProvenanceMode,
+    pub derivation: DerivationScheme,
+    pub config_owner: Option<Address>,
+}
+
+impl Config {
+    pub fn from_cli(cli: Cli) -> Result<Config, ConfigError> {
+        let drand = read_drand_config(&cli.config_path)?;
+        let provenance_mode =
+            provenance_mode_from_env().map_err(|e| invalid_env("DRAND_PROVENANCE", e))?;
+        let derivation =
+            derivation_scheme_from_env().map_err(|e| invalid_env("DRAND_DERIVATION", e))?;
+        let config_owner =
+            config_owner_from_env().map_err(|e| invalid_env("DRAND_CONFIG_OWNER", e))?;
+
+        Ok(Config {
+            bind_address: cli.bind_address,
+            port: cli.port,
+            journal_path: cli
+                .journal_path
+                .unwrap_or_else(|| cli.config_path.with_file_name(JOURNAL_FILE_NAME)),
+            config_path: cli.config_path,
+            log_format: cli.log_format,
+            rollup_url: cli.rollup_http_server_url.trim_end_matches('/').to_string(),
+            drand,
+            provenance_mode,
+            derivation,
+            config_owner,
+        })
+    }
+    /**
+     * Configuration without flags, as the middleware would start with only its env
+     */
+    #[cfg(test)]
+    pub fn from_env() -> Result<Config, ConfigError> {
+        let cli = Cli::try_parse_from(["cartesi-drand"]).map_err(|e| ConfigError::Env {
+            name: "flags".to_string(),
+            cause: e.to_string(),
+        })?;
+        Config::from_cli(cli)
+    }
+}
+
+fn invalid_env(name: &str, cause: impl ToString) -> ConfigError {
+    ConfigError::Env {
+        name: name.to_string(),
+        cause: cause.to_string(),
+    }
+}
+
+fn read_drand_config(path: &Path) -> Result<DrandConfig, ConfigError> {
+    let invalid_file = |cause: String| ConfigError::File {
+        path: path.display().to_string(),
+        cause,
+    };
+    let env = drand_env_from_env().map_err(|e| invalid_env("DRAND_*", e))?;
+
+    let drand = match fs::read_to_string(path) {
+        Ok(content) => {
+            let file = serde_json::from_str::<DrandEnv>(&content)
+                .map_err(|e| invalid_file(e.to_string()))?;
+            with_env_fallback(file, env)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => env,
+        Err(e) => return Err(invalid_file(e.to_string())),
+    };
+
+    let drand = DrandConfig::from_env(&drand).map_err(|cause| ConfigError::Drand { cause })?;
+    for chain in &drand.chains {
+        validate_drand_chain(chain).map_err(|e| ConfigError::Drand {
+            cause: e.to_string(),
+        })?;
+    }
+    Ok(drand)
+}
+
+fn with_env_fallback(file: DrandEnv, env: DrandEnv) -> DrandEnv {
+    DrandEnv {
+        DRAND_PUBLIC_KEY: match file.DRAND_PUBLIC_KEY.is_empty() {
+            true => env.DRAND_PUBLIC_KEY,
+            false => file.DRAND_PUBLIC_KEY,
+        },
+        DRAND_PERIOD: file.DRAND_PERIOD.or(env.DRAND_PERIOD),
+        DRAND_GENESIS_TIME: file.DRAND_GENESIS_TIME.or(env.DRAND_GENESIS_TIME),
+        DRAND_SAFE_SECONDS: file.DRAND_SAFE_SECONDS.or(env.DRAND_SAFE_SECONDS),
+        DRAND_SCHEME: file.DRAND_SCHEME.or(env.DRAND_SCHEME),
+        DRAND_CHAIN_HASH: file.DRAND_CHAIN_HASH.or(env.DRAND_CHAIN_HASH),
+        DRAND_EFFECTIVE_TIME: file.DRAND_EFFECTIVE_TIME,
+        DRAND_ROTATIONS: file.DRAND_ROTATIONS,
+    }
+}
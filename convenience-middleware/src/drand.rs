@@ -1,14 +1,20 @@
-use std::{borrow::BorrowMut, error::Error};
+use std::error::Error;
 
 use actix_web::web::Data;
-use dotenvy::var;
-use drand_verify::{derive_randomness, G2PubkeyRfc, Pubkey};
+use cartesi_rollup_metadata::Address;
+use drand_verify::{derive_randomness, G1Pubkey, G2PubkeyFastnet, G2PubkeyRfc, Pubkey};
 use log::{error, warn};
 use serde_json::json;
+use sha2::{Digest, Sha256};
 
 use crate::{
-    models::structs::{AppState, DrandBeacon, PayloadWithBeacon},
+    errors::CheckerError,
+    models::structs::{
+        AppState, ChainInfo, DrandBeacon, DrandChain, DrandConfig, DrandEnv, DrandScheme,
+        PayloadWithBeacon, PayloadWithBeacons, PayloadWithDrandConfig, RejectedBeacon,
+    },
     rollup::{input::RollupInput, server::send_report},
+    utils::util::generate_payload_hex,
 };
 
 pub fn is_querying_pending_beacon(rollup_input: &RollupInput) -> Result<bool, Box<dyn Error>> {
@@ -16,49 +22,233 @@ pub fn is_querying_pending_beacon(rollup_input: &RollupInput) -> Result<bool, Bo
     Ok(result == "pendingdrandbeacon")
 }
 
+pub fn is_querying_status(rollup_input: &RollupInput) -> Result<bool, Box<dyn Error>> {
+    let result = rollup_input.decoded_inspect()?;
+    Ok(result == "drandstatus")
+}
+
+pub async fn send_status_report(app_state: &Data<AppState>) {
+    let status = app_state.status().await;
+    let report = match generate_payload_hex(json!({ "drand_status": status })) {
+        Ok(payload) => json!({ "payload": payload }),
+        Err(e) => {
+            error!("Error encoding the status report: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = send_report(&app_state.rollup_url, report).await {
+        error!("Error sending status report: {}", e);
+    }
+}
+
+/**
+ * Answer the pendingdrandbeacon inspect with two reports.
+ * The first one keeps the old form, the pending timestamp as a hex number.
+ * The second one has the JSON {"pending_drand_beacon": {...}} with the round to send.
+ */
 pub async fn send_pending_beacon_report(app_state: &Data<AppState>) {
-    let manager = app_state.input_buffer_manager.lock().await;
-    let x = manager.pending_beacon_timestamp.get();
+    let pending = app_state.pending_beacon().await;
+    let x = pending.timestamp;
     let report = json!({ "payload": format!("{x:#x}") });
-    let _ = send_report(report).await.unwrap();
+    if let Err(e) = send_report(&app_state.rollup_url, report).await {
+        error!("Error sending pending beacon report: {}", e);
+    }
+
+    let report = match generate_payload_hex(json!({ "pending_drand_beacon": pending })) {
+        Ok(payload) => json!({ "payload": payload }),
+        Err(e) => {
+            error!("Error encoding the pending beacon report: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = send_report(&app_state.rollup_url, report).await {
+        error!("Error sending pending beacon report: {}", e);
+    }
 }
 
 /**
- * Check if the request is a drand beacon
+ * A verified beacon with its time, or why it was rejected
+ */
+pub type BeaconVerification = Result<(DrandBeacon, u64), RejectedBeacon>;
+
+/**
+ * Check if the request carries drand beacons
  * Example of a drand beacon request
  *
  * {"beacon":{"round":3828300,"randomness":"7ff726d290836da706126ada89f7e99295c672d6768ec8e035fd3de5f3f35cd9","signature":"ab85c071a4addb83589d0ecf5e2389f7054e4c34e0cbca65c11abc30761f29a0d338d0d307e6ebcb03d86f781bc202ee"}}
+ *
+ * Chained networks also carry the previous_signature
+ *
+ * {"beacon":{"round":72785,"randomness":"...","signature":"82f5d3...","previous_signature":"a609e1..."}}
+ *
+ * Many beacons can be sent at once to catch up after a downtime
+ *
+ * {"beacons":[{"round":3828300,"randomness":"...","signature":"ab85c0..."},{"round":3828301,"randomness":"...","signature":"8f1b2d..."}]}
+ *
+ * The signatures are verified in parallel. Returns each beacon, in the payload order,
+ * verified with its time on the chain whose key signed it, or the reason it was rejected
  */
-pub fn get_drand_beacon(payload: &str) -> Result<DrandBeacon, Box<dyn std::error::Error>> {
-    let key = var("DRAND_PUBLIC_KEY").expect("Public Key not found");
-
+pub fn get_drand_beacons(
+    drand: &DrandConfig,
+    payload: &str,
+) -> Result<Vec<BeaconVerification>, Box<dyn std::error::Error>> {
     let payload = payload.trim_start_matches("0x");
     let payload = hex::decode(payload)?;
-    let payload = std::str::from_utf8(&payload).map(|s| s.to_owned())?;
+    let beacons = match serde_json::from_slice::<PayloadWithBeacons>(&payload) {
+        Ok(payload) => payload.beacons,
+        Err(_) => vec![serde_json::from_slice::<PayloadWithBeacon>(&payload)?.beacon],
+    };
+
+    let verify = |beacons: &[DrandBeacon]| -> Vec<BeaconVerification> {
+        beacons
+            .iter()
+            .map(|beacon| {
+                verify_on_drand_chains(drand, beacon).map_err(|e| RejectedBeacon {
+                    round: beacon.round,
+                    cause: e.to_string(),
+                })
+            })
+            .collect()
+    };
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if beacons.len() < 2 || threads < 2 {
+        return Ok(verify(&beacons));
+    }
 
-    let payload = serde_json::from_str::<PayloadWithBeacon>(&payload)?;
+    let chunk_size = beacons.len().div_ceil(threads);
+    let results = std::thread::scope(|scope| {
+        let handles: Vec<_> = beacons
+            .chunks(chunk_size)
+            .map(|chunk| (chunk, scope.spawn(move || verify(chunk))))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|(chunk, handle)| {
+                handle.join().unwrap_or_else(|_| {
+                    let rejected = |beacon: &DrandBeacon| RejectedBeacon {
+                        round: beacon.round,
+                        cause: "Beacon verification failed".to_string(),
+                    };
+                    chunk.iter().map(|beacon| Err(rejected(beacon))).collect()
+                })
+            })
+            .collect()
+    });
+    Ok(results)
+}
 
-    let mut pk = [0u8; 96];
-    hex::decode_to_slice(&key, pk.borrow_mut())?;
+/**
+ * Around a rotation the round may belong to either chain, the signature tells which one
+ */
+fn verify_on_drand_chains(
+    drand: &DrandConfig,
+    beacon: &DrandBeacon,
+) -> Result<(DrandBeacon, u64), Box<dyn std::error::Error>> {
+    let round = beacon.round;
+    let mut result = Err(format!("Round {round} is outside of the drand chains").into());
+    for chain in drand.chains_for_round(round) {
+        // the chains of the round all have its time
+        let time = chain.timestamp_for_round(round).unwrap_or_default();
+        result = verify_drand_beacon(chain.scheme, &chain.public_key, beacon)
+            .map(|beacon| (beacon, time));
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
 
-    let pk = G2PubkeyRfc::from_fixed(pk).map_err(|e| e.to_string())?;
+/**
+ * Check if the request carries a drand config update, whoever sent it
+ */
+pub fn has_drand_config(rollup_input: &RollupInput) -> bool {
+    drand_config_payload(rollup_input).is_some()
+}
 
-    let signature = hex::decode(&payload.beacon.signature)?;
+fn drand_config_payload(rollup_input: &RollupInput) -> Option<PayloadWithDrandConfig> {
+    let payload = rollup_input.data.payload.trim_start_matches("0x");
+    let payload = hex::decode(payload).ok()?;
+    serde_json::from_slice::<PayloadWithDrandConfig>(&payload).ok()
+}
 
-    let round = payload.beacon.round;
+/**
+ * Check if the request is a drand config update, which only the config owner may send
+ *
+ * {"drand_config":{"DRAND_PUBLIC_KEY":"83cf0f...","DRAND_PERIOD":3,"DRAND_GENESIS_TIME":1692803367,"DRAND_EFFECTIVE_TIME":1700000000}}
+ *
+ * None when the input is something else
+ */
+pub fn get_drand_config_update(
+    owner: Option<&Address>,
+    rollup_input: &RollupInput,
+) -> Option<Result<DrandEnv, CheckerError>> {
+    let payload = drand_config_payload(rollup_input)?;
 
-    match pk.verify(round, b"", &signature) {
+    let sender = rollup_input
+        .data
+        .metadata
+        .as_ref()
+        .map(|metadata| metadata.msg_sender);
+    let result = match (owner, sender) {
+        (None, _) => Err("no DRAND_CONFIG_OWNER is set".to_string()),
+        (Some(owner), Some(sender)) if *owner == sender => Ok(payload.drand_config),
+        (Some(_), Some(sender)) => Err(format!("{} is not the config owner", sender)),
+        (Some(_), None) => Err("the input has no sender".to_string()),
+    };
+    Some(result.map_err(|cause| CheckerError::UnauthorizedDrandConfig { cause }))
+}
+
+/**
+ * Verify the beacon signature against the public key using the given scheme
+ * and derive the randomness from the signature
+ */
+pub fn verify_drand_beacon(
+    scheme: DrandScheme,
+    key: &str,
+    drand_beacon: &DrandBeacon,
+) -> Result<DrandBeacon, Box<dyn std::error::Error>> {
+    let pk = hex::decode(key)?;
+    let signature = hex::decode(&drand_beacon.signature)?;
+    let round = drand_beacon.round;
+
+    let result = match scheme {
+        DrandScheme::PedersenBlsChained => {
+            let previous_signature = drand_beacon
+                .previous_signature
+                .as_ref()
+                .ok_or("Chained beacon without previous_signature")?;
+            let previous_signature = hex::decode(previous_signature)?;
+
+            G1Pubkey::from_variable(&pk)
+                .map_err(|e| e.to_string())?
+                .verify(round, &previous_signature, &signature)
+        }
+        DrandScheme::PedersenBlsUnchained => G1Pubkey::from_variable(&pk)
+            .map_err(|e| e.to_string())?
+            .verify(round, b"", &signature),
+        DrandScheme::BlsUnchainedOnG1 => G2PubkeyFastnet::from_variable(&pk)
+            .map_err(|e| e.to_string())?
+            .verify(round, b"", &signature),
+        DrandScheme::BlsUnchainedG1Rfc9380 => G2PubkeyRfc::from_variable(&pk)
+            .map_err(|e| e.to_string())?
+            .verify(round, b"", &signature),
+    };
+
+    match result {
         Ok(valid) => {
             if !valid {
                 let msg = format!(
-                    "Invalid beacon signature for round {}; signature: {}; public_key: {};",
-                    round, &payload.beacon.signature, key
+                    "Invalid beacon signature for round {}; signature: {}; public_key: {}; scheme: {};",
+                    round,
+                    &drand_beacon.signature,
+                    key,
+                    scheme.as_str()
                 );
 
                 warn!("{msg}");
                 return Err(msg.into());
             }
-            let mut beacon = payload.beacon.to_owned();
+            let mut beacon = drand_beacon.to_owned();
 
             // make sure that the signature is the source of randomness
             beacon.randomness = hex::encode(derive_randomness(&signature));
@@ -70,3 +260,110 @@ pub fn get_drand_beacon(payload: &str) -> Result<DrandBeacon, Box<dyn std::error
         }
     }
 }
+
+fn invalid_config(field: &str, cause: impl ToString) -> CheckerError {
+    CheckerError::InvalidDrandConfig {
+        field: field.to_string(),
+        cause: cause.to_string(),
+    }
+}
+
+/**
+ * Check that the public key is a point of the group used by the scheme
+ */
+pub fn validate_public_key(scheme: DrandScheme, key: &str) -> Result<(), CheckerError> {
+    let pk = hex::decode(key).map_err(|e| invalid_config("public_key", e))?;
+
+    let result = match scheme {
+        DrandScheme::PedersenBlsChained | DrandScheme::PedersenBlsUnchained => {
+            G1Pubkey::from_variable(&pk).map(|_| ())
+        }
+        DrandScheme::BlsUnchainedOnG1 => G2PubkeyFastnet::from_variable(&pk).map(|_| ()),
+        DrandScheme::BlsUnchainedG1Rfc9380 => G2PubkeyRfc::from_variable(&pk).map(|_| ()),
+    };
+
+    result.map_err(|e| {
+        invalid_config(
+            "public_key",
+            format!("not a valid point for {}: {}", scheme.as_str(), e),
+        )
+    })
+}
+
+/**
+ * Check the chain built from the values sent to /update_drand_config
+ */
+pub fn validate_drand_chain(chain: &DrandChain) -> Result<(), CheckerError> {
+    if chain.period == 0 {
+        return Err(invalid_config(
+            "DRAND_PERIOD",
+            "the period must not be zero",
+        ));
+    }
+    validate_public_key(chain.scheme, &chain.public_key)
+}
+
+/**
+ * Chain hash as computed by drand, big-endian:
+ *
+ * SHA-256(period as u32 || genesis_time as i64 || public_key || group_hash || beacon_id)
+ *
+ * The beacon id is left out for the default chain.
+ */
+pub fn chain_hash(info: &ChainInfo) -> Result<[u8; 32], CheckerError> {
+    let period = u32::try_from(info.period).map_err(|e| invalid_config("period", e))?;
+    let genesis_time =
+        i64::try_from(info.genesis_time).map_err(|e| invalid_config("genesis_time", e))?;
+    let public_key = hex::decode(&info.public_key).map_err(|e| invalid_config("public_key", e))?;
+    let group_hash = hex::decode(&info.group_hash).map_err(|e| invalid_config("groupHash", e))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(period.to_be_bytes());
+    hasher.update(genesis_time.to_be_bytes());
+    hasher.update(public_key);
+    hasher.update(group_hash);
+    let beacon_id = info.metadata.beacon_id.as_str();
+    if !beacon_id.is_empty() && beacon_id != "default" {
+        hasher.update(beacon_id.as_bytes());
+    }
+    Ok(hasher.finalize().into())
+}
+
+/**
+ * Validate the drand /info document and turn it into the config of the middleware,
+ * keeping the current safe seconds
+ */
+pub fn drand_env_from_chain_info(info: &ChainInfo) -> Result<DrandEnv, CheckerError> {
+    let scheme = match &info.scheme_id {
+        Some(scheme_id) => scheme_id
+            .parse::<DrandScheme>()
+            .map_err(|e| invalid_config("schemeID", e))?,
+        None => DrandScheme::PedersenBlsChained,
+    };
+    if info.period == 0 {
+        return Err(invalid_config("period", "the period must not be zero"));
+    }
+
+    let hash = chain_hash(info)?;
+    if hex::encode(hash) != info.hash.to_lowercase() {
+        return Err(invalid_config(
+            "hash",
+            format!(
+                "expected {} from the chain info, got {}",
+                hex::encode(hash),
+                info.hash
+            ),
+        ));
+    }
+    validate_public_key(scheme, &info.public_key)?;
+
+    Ok(DrandEnv {
+        DRAND_PUBLIC_KEY: info.public_key.clone(),
+        DRAND_PERIOD: Some(info.period),
+        DRAND_GENESIS_TIME: Some(info.genesis_time),
+        DRAND_SAFE_SECONDS: None,
+        DRAND_SCHEME: Some(scheme),
+        DRAND_CHAIN_HASH: Some(info.hash.to_lowercase()),
+        ..Default::default()
+    })
+}
@@ -3,10 +3,8 @@ use derive_more::{Display, Error};
 
 #[derive(Debug, Display, Error)]
 pub enum CheckerError {
-    #[display(fmt = "Error updating drand config: {}", cause)]
-    InvalidDrandConfig {
-        cause: String,
-    },
+    #[display(fmt = "Error updating drand config, invalid {}: {}", field, cause)]
+    InvalidDrandConfig { field: String, cause: String },
 
     #[display(fmt = "Already inspecting")]
     AlreadyInspecting,
@@ -28,6 +26,69 @@ pub enum CheckerError {
 
     #[display(fmt = "Error getting randomness")]
     RandomnessError,
+
+    #[display(fmt = "Invalid random request: {}", cause)]
+    InvalidRandomQuery { cause: String },
+
+    #[display(fmt = "Invalid tlock ciphertext: {}", cause)]
+    InvalidTlockCiphertext { cause: String },
+
+    #[display(fmt = "Unexpected encoding of the tlock pairing: {}", cause)]
+    TlockGtEncoding { cause: String },
+
+    #[display(fmt = "Timelock encryption is not available on the {} scheme", scheme)]
+    TlockUnsupportedScheme { scheme: String },
+
+    #[display(
+        fmt = "The beacon of round {} is older than the whole beacon history and can't be kept anymore",
+        round
+    )]
+    RoundTooOld { round: u64 },
+
+    #[display(fmt = "Unauthorized drand config update: {}", cause)]
+    UnauthorizedDrandConfig { cause: String },
+}
+
+/**
+ * Invalid configuration found at startup
+ */
+#[derive(Debug, Display, Error)]
+pub enum ConfigError {
+    #[display(fmt = "Invalid env {}: {}", name, cause)]
+    Env { name: String, cause: String },
+
+    #[display(fmt = "Invalid config file {}: {}", path, cause)]
+    File { path: String, cause: String },
+
+    #[display(fmt = "Invalid drand config: {}", cause)]
+    Drand { cause: String },
+
+    #[display(fmt = "Unreadable journal {}: {}", path, cause)]
+    Journal { path: String, cause: String },
+}
+
+impl CheckerError {
+    /**
+     * Stable name of the error, for the clients that should not parse the message
+     */
+    pub fn code(&self) -> &'static str {
+        match self {
+            CheckerError::InvalidDrandConfig { .. } => "InvalidDrandConfig",
+            CheckerError::AlreadyInspecting => "AlreadyInspecting",
+            CheckerError::SendRollupAndRetrieveInputError => "SendRollupAndRetrieveInputError",
+            CheckerError::ByPassInspect => "ByPassInspect",
+            CheckerError::UnknownRequestType => "UnknownRequestType",
+            CheckerError::StoreInputByPass => "StoreInputByPass",
+            CheckerError::SignatureErrorBeacon => "SignatureErrorBeacon",
+            CheckerError::RandomnessError => "RandomnessError",
+            CheckerError::InvalidRandomQuery { .. } => "InvalidRandomQuery",
+            CheckerError::InvalidTlockCiphertext { .. } => "InvalidTlockCiphertext",
+            CheckerError::TlockGtEncoding { .. } => "TlockGtEncoding",
+            CheckerError::TlockUnsupportedScheme { .. } => "TlockUnsupportedScheme",
+            CheckerError::RoundTooOld { .. } => "RoundTooOld",
+            CheckerError::UnauthorizedDrandConfig { .. } => "UnauthorizedDrandConfig",
+        }
+    }
 }
 
 impl serde::Serialize for CheckerError {
@@ -35,12 +96,16 @@ impl serde::Serialize for CheckerError {
     where
         S: serde::Serializer,
     {
-        let response = serde_json::json!({
-            "error": self.to_string(),
-        })
-        .to_string();
+        use serde::ser::SerializeMap;
 
-        serializer.serialize_str(&response)
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        map.serialize_entry("error", &self.to_string())?;
+        if let CheckerError::InvalidDrandConfig { field, cause } = self {
+            map.serialize_entry("field", field)?;
+            map.serialize_entry("cause", cause)?;
+        }
+        map.end()
     }
 }
 
@@ -55,6 +120,12 @@ impl error::ResponseError for CheckerError {
             CheckerError::StoreInputByPass => hyper::StatusCode::BAD_REQUEST,
             CheckerError::SignatureErrorBeacon => hyper::StatusCode::BAD_REQUEST,
             CheckerError::RandomnessError => hyper::StatusCode::BAD_REQUEST,
+            CheckerError::InvalidRandomQuery { .. } => hyper::StatusCode::BAD_REQUEST,
+            CheckerError::InvalidTlockCiphertext { .. } => hyper::StatusCode::BAD_REQUEST,
+            CheckerError::TlockGtEncoding { .. } => hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            CheckerError::TlockUnsupportedScheme { .. } => hyper::StatusCode::BAD_REQUEST,
+            CheckerError::RoundTooOld { .. } => hyper::StatusCode::NOT_FOUND,
+            CheckerError::UnauthorizedDrandConfig { .. } => hyper::StatusCode::FORBIDDEN,
         }
     }
 
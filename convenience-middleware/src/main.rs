@@ -1,31 +1,66 @@
+mod config;
 mod drand;
 mod errors;
 mod main_test;
+mod metrics;
 mod models;
+mod randomness;
 mod rollup;
 mod router;
+mod tlock;
 mod utils;
 
+use std::io::Write;
+
+use crate::config::{Cli, Config, LogFormat};
 use crate::models::structs::AppState;
 use crate::router::routes;
-use crate::utils::util::load_env_from_json;
 use actix_web::{middleware::Logger, web, App, HttpServer};
-use log::info;
+use clap::Parser;
+use log::{error, info};
+use serde_json::json;
+
+fn init_logger(format: LogFormat) {
+    let env = env_logger::Env::default().default_filter_or("info");
+    let mut builder = env_logger::builder();
+    builder.parse_env(env).format_timestamp(None);
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = json!({
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    if let Err(e) = builder.try_init() {
+        eprintln!("Error initializing the logger: {}", e);
+    }
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    load_env_from_json().await.unwrap();
+    dotenvy::dotenv().ok();
+    let config = match Config::from_cli(Cli::parse()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
-    let env = env_logger::Env::default().default_filter_or("info");
-    env_logger::builder()
-        .parse_env(env)
-        .format_timestamp(None)
-        .try_init()
-        .unwrap();
+    init_logger(config.log_format);
 
-    let app_state = web::Data::new(AppState::new());
+    let app_state = match AppState::new(&config) {
+        Ok(app_state) => web::Data::new(app_state),
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
-    info!("Starting server");
+    info!("Starting server on {}:{}", config.bind_address, config.port);
 
     HttpServer::new(move || {
         let logger = Logger::default();
@@ -33,11 +68,25 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(logger)
             .app_data(app_state.clone())
+            .service(routes::middleware_status)
+            .service(routes::metrics)
             .service(routes::request_random)
+            .service(routes::request_random_bytes)
+            .service(routes::request_random_range)
+            .service(routes::request_random_shuffle)
+            .service(routes::request_random_batch)
+            .service(routes::tlock_decrypt)
+            .service(routes::round_for_timestamp)
+            .service(routes::timestamp_for_round)
             .service(routes::consume_buffer)
+            .service(routes::notice)
+            .service(routes::voucher)
+            .service(routes::report)
+            .service(routes::exception)
             .service(routes::update_drand_config)
+            .service(routes::update_drand_chain_info)
     })
-    .bind(("0.0.0.0", 8080))?
+    .bind((config.bind_address.as_str(), config.port))?
     .run()
     .await
 }
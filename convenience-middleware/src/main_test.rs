@@ -1,13 +1,23 @@
 #[cfg(test)]
 mod middleware_tests {
+    use clap::Parser;
     use hex_literal::hex;
     use std::{error::Error, sync::Once};
 
     use crate::{
-        drand::get_drand_beacon,
-        models::structs::{AppState, Beacon, DrandBeacon},
+        config::{Cli, Config, LogFormat},
+        drand::{drand_env_from_chain_info, get_drand_beacons, verify_drand_beacon},
+        errors::{CheckerError, ConfigError},
+        models::structs::{
+            AppState, Beacon, ChainInfo, DerivationScheme, DrandBeacon, DrandConfig, DrandEnv,
+            DrandScheme, Draw, LifecycleState, PayloadWithBeacon, Provenance, ProvenanceMode,
+            RoundTimestamp, BEACON_HISTORY_SIZE,
+        },
+        randomness::{derive_seed, expand_seed, sample_range, shuffle},
+        rollup::input::{has_input_inside_input, RollupInput},
         router::routes::{self},
-        utils::util::{generate_payload_hex, load_env_from_json},
+        tlock,
+        utils::util::{generate_payload_hex, journal_log_path},
     };
     use actix_web::{
         http::{self},
@@ -16,6 +26,8 @@ mod middleware_tests {
         web::{self},
         App,
     };
+    use bls12_381::{pairing, G1Affine, G2Affine, G2Projective, Gt};
+    use cartesi_rollup_metadata::Address;
     use dotenvy::dotenv;
     use drand_verify::{G2Pubkey, G2PubkeyRfc, Pubkey as _};
     use http::Method;
@@ -30,7 +42,6 @@ mod middleware_tests {
     macro_rules! check_if_dotenv_is_loaded {
         () => {{
             dotenv().unwrap();
-            load_env_from_json().await.unwrap();
         }};
     }
 
@@ -38,6 +49,17 @@ mod middleware_tests {
     static BIND_SERVER: Once = Once::new();
     static BIND_LOGGER: Once = Once::new();
 
+    #[macro_export]
+    macro_rules! bind_rollup_server {
+        ($server:expr) => {
+            BIND_SERVER.call_once(|| {
+                let url = $server.url_str("");
+                let url = url.trim_end_matches("/");
+                std::env::set_var("ROLLUP_HTTP_SERVER_URL", url);
+            });
+        };
+    }
+
     #[macro_export]
     macro_rules! mock_rollup_server {
         ($x:expr ) => {
@@ -51,19 +73,25 @@ mod middleware_tests {
                 .times(1..)
                 .respond_with($x),
             );
+            // the reports of the inputs consumed by the middleware
+            server.expect(
+                Expectation::matching(request::method_path(
+                    hyper::Method::POST.as_str(),
+                    "/report",
+                ))
+                .times(..)
+                .respond_with(status_code(200)),
+            );
 
-            BIND_SERVER.call_once(|| {
-                let url = server.url_str("");
-                let url = url.trim_end_matches("/");
-                std::env::set_var("ROLLUP_HTTP_SERVER_URL", url);
-            });
+            bind_rollup_server!(server);
         };
     }
 
     #[macro_export]
     macro_rules! call_random {
         ($app:expr) => {{
-            let req = test::TestRequest::with_uri("/random?timestamp=1")
+            let uri = format!("/random?timestamp={}", timestamp_before_round(2832127));
+            let req = test::TestRequest::with_uri(uri.as_str())
                 .method(Method::GET)
                 .to_request();
             let result = test::call_and_read_body($app, req).await;
@@ -85,6 +113,14 @@ mod middleware_tests {
         }};
     }
 
+    /**
+     * A query timestamp whose draw comes from the beacon of the round
+     */
+    fn timestamp_before_round(round: u64) -> u64 {
+        let app_state = AppState::with_journal(None);
+        app_state.timestamp_for_round(round).unwrap() - app_state.safe_seconds() - 1
+    }
+
     fn generate_log() -> Logger {
         BIND_LOGGER.call_once(|| {
             let env = env_logger::Env::default().default_filter_or("info");
@@ -120,7 +156,7 @@ mod middleware_tests {
         check_if_dotenv_is_loaded!();
         mock_rollup_server!(status_code(202));
 
-        let app_state = web::Data::new(AppState::new());
+        let app_state = web::Data::new(AppState::with_journal(None));
         let manager = app_state.input_buffer_manager.clone();
 
         let logger = generate_log();
@@ -146,7 +182,7 @@ mod middleware_tests {
         assert_eq!(resp.status(), 400);
         assert_eq!(
             manager.lock().await.pending_beacon_timestamp.get(),
-            timestamp + app_state.safe_seconds
+            timestamp + app_state.safe_seconds()
         );
     }
 
@@ -158,13 +194,15 @@ mod middleware_tests {
 
         let beacon = Beacon::builder()
             .with_round(1)
-            .with_randomness("to-be-a-seed".to_string())
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
             .with_timestamp(last_clock_beacon)
             .build();
 
-        let app_state = web::Data::new(AppState::new());
+        let app_state = web::Data::new(AppState::with_journal(None));
         let manager = app_state.input_buffer_manager.clone();
-        manager.lock().await.last_beacon.set(Some(beacon));
+        manager.lock().await.add_beacon(beacon);
 
         let logger = generate_log();
 
@@ -184,24 +222,24 @@ mod middleware_tests {
         let status = resp.status();
         assert!(status.is_client_error(), "status: {:?}", status.as_str());
         assert_eq!(status, 400);
-        assert!(manager.lock().await.last_beacon.get_mut().is_some());
+        assert!(manager.lock().await.last_beacon().is_some());
     }
 
     #[actix_web::test]
     async fn request_random_with_new_beacon_old_request() {
         check_if_dotenv_is_loaded!();
 
-        let last_clock_beacon = 24;
-
-        let beacon = Beacon::builder()
-            .with_round(1)
-            .with_randomness("to-be-a-seed".to_string())
-            .with_timestamp(last_clock_beacon)
-            .build();
-
-        let app_state = web::Data::new(AppState::new());
+        let app_state = web::Data::new(AppState::with_journal(None));
         let manager = app_state.input_buffer_manager.clone();
-        manager.lock().await.last_beacon.set(Some(beacon));
+        for round in [10, 15] {
+            let beacon = DrandBeacon::builder()
+                .with_round(round)
+                .with_randomness(
+                    "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+                )
+                .build();
+            app_state.keep_beacon(beacon).await;
+        }
 
         let logger = generate_log();
 
@@ -212,9 +250,8 @@ mod middleware_tests {
 
         let app = test::init_service(app).await;
 
-        let old_clock = last_clock_beacon - 10;
-
-        let uri = format!("/random?timestamp={}", &old_clock);
+        // the round of the old request is known, even if newer beacons arrived after it
+        let uri = format!("/random?timestamp={}", timestamp_before_round(10));
         let req = test::TestRequest::with_uri(uri.as_str()).to_request();
 
         let resp = test::call_service(&app, req).await;
@@ -222,199 +259,2123 @@ mod middleware_tests {
         assert!(status.is_success(), "status: {:?}", status.as_str());
         assert_eq!(status, 200);
 
-        assert!(!manager.lock().await.flag_to_hold.is_holding);
-        assert!(manager.lock().await.last_beacon.get_mut().is_some());
+        assert!(!manager.lock().await.state.is_holding());
+        assert_eq!(manager.lock().await.last_beacon().unwrap().round, 15);
     }
 
     #[actix_web::test]
-    async fn test_request_finish_without_input_to_respond() {
+    async fn request_random_by_round() {
         check_if_dotenv_is_loaded!();
         mock_rollup_server!(status_code(202));
 
-        let app_state = web::Data::new(AppState::new());
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let manager = app_state.input_buffer_manager.clone();
+        let genesis = app_state.timestamp_for_round(0).unwrap();
+
+        let beacon = DrandBeacon::builder()
+            .with_round(6)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon.clone()).await;
 
         let logger = generate_log();
         let app = App::new()
             .wrap(logger)
             .app_data(app_state.clone())
-            .service(routes::consume_buffer);
+            .service(routes::request_random)
+            .service(routes::round_for_timestamp)
+            .service(routes::timestamp_for_round);
 
         let app = test::init_service(app).await;
 
-        // the DApp call our middleware
-        let req = test::TestRequest::with_uri("/finish")
-            .method(Method::POST)
-            .set_json(json!({"status": "accept"}))
-            .to_request();
+        // genesis + 10 + 5 safe seconds is before round 6 at genesis + 18
+        let uri = format!("/round_for_timestamp?timestamp={}", genesis + 10);
+        let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+        let resp: RoundTimestamp = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            resp,
+            RoundTimestamp {
+                round: 6,
+                timestamp: genesis + 18
+            }
+        );
+
+        let req = test::TestRequest::with_uri("/timestamp_for_round?round=6").to_request();
+        let resp: RoundTimestamp = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.timestamp, genesis + 18);
+
+        let req = test::TestRequest::with_uri("/random?round=6").to_request();
+        let by_round = test::call_and_read_body(&app, req).await;
+
+        // the timestamp mode gives the same draw
+        let other_state = web::Data::new(AppState::with_journal(None));
+        other_state.keep_beacon(beacon).await;
+        let by_timestamp = other_state
+            .get_randomness_for_timestamp(genesis + 10, "")
+            .await;
+        assert_eq!(
+            std::str::from_utf8(&by_round).ok(),
+            by_timestamp.map(hex::encode).as_deref()
+        );
 
+        // round 7 did not arrive yet
+        let req = test::TestRequest::with_uri("/random?round=7").to_request();
         let resp = test::call_service(&app, req).await;
-        assert_eq!(resp.status(), 202);
+        assert_eq!(resp.status(), 400);
+        assert_eq!(
+            manager.lock().await.pending_beacon_timestamp.get(),
+            genesis + 18
+        );
+
+        let req = test::TestRequest::with_uri("/random?round=6&timestamp=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::with_uri("/random").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        // a time past u64 is refused before holding for a beacon that never comes
+        for uri in [
+            format!("/random?timestamp={}", u64::MAX),
+            format!("/random?round={}", u64::MAX),
+            format!("/round_for_timestamp?timestamp={}", u64::MAX),
+            format!("/timestamp_for_round?round={}", u64::MAX),
+        ] {
+            let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["code"], "InvalidRandomQuery");
+        }
+        assert_eq!(
+            manager.lock().await.pending_beacon_timestamp.get(),
+            genesis + 18
+        );
     }
 
     #[actix_web::test]
-    async fn test_request_finish_with_input_to_respond() {
+    async fn request_random_by_skipped_round() {
         check_if_dotenv_is_loaded!();
 
-        let payload = mock_factory(None).unwrap();
+        // quicknet round 123, sent by the provider once the DApp is held for it
+        let beacon = json!({"round":123,"randomness":"","signature":"b75c69d0b72a5d906e854e808ba7e2accb1542ac355ae486d591aa9d43765482e26cd02df835d3546d23c4b13e0dfc92"});
+        mock_rollup_server!(json_encoded(
+            mock_factory(Some(json!({ "beacon": beacon }))).unwrap()
+        ));
+
+        let newer = DrandBeacon::builder()
+            .with_round(2797373)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        let app_state = web::Data::new(AppState::with_journal(None));
+        app_state.keep_beacon(newer).await;
+
+        // a verified beacon draws from the randomness of its signature
+        let mut verified: DrandBeacon = serde_json::from_value(beacon).unwrap();
+        verified.randomness = hex::encode(drand_verify::derive_randomness(
+            &hex::decode(&verified.signature).unwrap(),
+        ));
+        let other_state = AppState::with_journal(None);
+        other_state.keep_beacon(verified).await;
+        let round_123 = other_state.get_randomness_for_round(123, "").await.unwrap();
 
-        mock_rollup_server!(json_encoded(payload));
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::request_random);
+        let app = test::init_service(app).await;
+
+        // round 123 never arrived and a newer beacon must not answer for it, so the DApp is held until it does
+        let req = test::TestRequest::with_uri("/random?round=123").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(body, hex::encode(round_123).as_bytes());
+        assert_eq!(app_state.metrics.finish_roundtrips.get(), 1);
+
+        // a round older than a full history would never be kept
+        let app_state = web::Data::new(AppState::with_journal(None));
+        for round in 1000..1000 + BEACON_HISTORY_SIZE as u64 {
+            app_state
+                .keep_beacon(DrandBeacon::builder().with_round(round).build())
+                .await;
+        }
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::request_random);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::with_uri("/random?round=123").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "RoundTooOld");
+        assert_eq!(
+            body["error"],
+            CheckerError::RoundTooOld { round: 123 }.to_string()
+        );
+        assert_eq!(app_state.metrics.finish_roundtrips.get(), 0);
+    }
+
+    #[actix_web::test]
+    async fn request_random_sends_provenance_notice_per_input() {
+        check_if_dotenv_is_loaded!();
+
+        let server = SERVER.get_server();
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/notice",
+            ))
+            .times(1)
+            .respond_with(json_encoded(json!({"index": 0}))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(1)
+            .respond_with(status_code(202)),
+        );
+        bind_rollup_server!(server);
+
+        let mut app_state = AppState::with_journal(None);
+        app_state.provenance_mode = ProvenanceMode::Input;
+        let app_state = web::Data::new(app_state);
+        let genesis = app_state.timestamp_for_round(0).unwrap();
 
-        let app_state = web::Data::new(AppState::new());
+        let beacon = DrandBeacon::builder()
+            .with_round(6)
+            .with_signature("b44679b9a59af2ec876b1a6b1ad52ea9b1615fc3982b19576350f93447cb1125e342b73a8dd2bacbe47e4b6b63ed5e39".to_string())
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon.clone()).await;
 
         let logger = generate_log();
         let app = App::new()
             .wrap(logger)
             .app_data(app_state.clone())
+            .service(routes::request_random)
             .service(routes::consume_buffer);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
-        // the DApp call our middleware /finish
-        let req = call_finish!(&mut app);
-        assert_eq!(req["request_type"], "advance_state");
+        let req = test::TestRequest::with_uri("/random?round=6").to_request();
+        let first = test::call_and_read_body(&app, req).await;
+        let req = test::TestRequest::with_uri("/random?round=6&domain=cards").to_request();
+        let second = test::call_and_read_body(&app, req).await;
+
+        // the draws of the input wait for its finish
+        let provenance = app_state
+            .input_buffer_manager
+            .lock()
+            .await
+            .provenance
+            .clone();
+        assert_eq!(
+            provenance,
+            vec![
+                Provenance {
+                    round: 6,
+                    signature: beacon.signature.clone(),
+                    timestamp: genesis + 18,
+                    input_index: 0,
+                    sequence: 0,
+                    domain: "".to_string(),
+                    derivation: DerivationScheme::Sha3_256,
+                    output: std::str::from_utf8(&first).unwrap().to_string(),
+                },
+                Provenance {
+                    round: 6,
+                    signature: beacon.signature.clone(),
+                    timestamp: genesis + 18,
+                    input_index: 0,
+                    sequence: 1,
+                    domain: "cards".to_string(),
+                    derivation: DerivationScheme::Sha3_256,
+                    output: std::str::from_utf8(&second).unwrap().to_string(),
+                },
+            ]
+        );
+
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+        assert!(app_state
+            .input_buffer_manager
+            .lock()
+            .await
+            .provenance
+            .is_empty());
     }
 
     #[actix_web::test]
-    async fn test_request_finish_with_beacon_inside_input() {
-        let empty = mock_factory(None).unwrap();
-
-        let randomness =
-            String::from("7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527");
-        let signature = String::from("8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352");
+    async fn request_random_bytes_by_round() {
+        check_if_dotenv_is_loaded!();
+        mock_rollup_server!(status_code(202));
 
         let beacon = DrandBeacon::builder()
-            .with_randomness(randomness)
-            .with_round(2832127)
-            .with_signature(signature)
-            .build()
-            .wrap();
-
-        let beacon = mock_factory(Some(beacon)).unwrap();
-
-        check_if_dotenv_is_loaded!();
-        mock_rollup_server!(responders::cycle![
-            json_encoded(empty),
-            json_encoded(beacon)
-        ]);
+            .with_round(6)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        let app_state = web::Data::new(AppState::with_journal(None));
+        app_state.keep_beacon(beacon.clone()).await;
+        let other_state = web::Data::new(AppState::with_journal(None));
+        other_state.keep_beacon(beacon).await;
 
         let logger = generate_log();
-        let app_state = web::Data::new(AppState::new());
-
         let app = App::new()
             .wrap(logger)
             .app_data(app_state.clone())
-            .service(routes::consume_buffer)
-            .service(routes::request_random);
+            .service(routes::request_random_bytes);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
-        // the DApp call our middleware to start something
-        let req = call_finish!(&mut app);
-        assert_eq!(req["request_type"], "advance_state");
+        let req = test::TestRequest::with_uri("/random/bytes?round=6&length=100").to_request();
+        let hex_bytes = test::call_and_read_body(&app, req).await;
 
-        // call again and the beacon arrives
-        let req = call_finish!(&mut app);
-        assert_eq!(req["request_type"], "advance_state");
+        let seed = other_state.get_randomness_for_round(6, "").await.unwrap();
+        let expected = expand_seed(&seed, 100);
+        assert_eq!(hex_bytes, hex::encode(&expected));
 
-        // check randomness
-        let randomness = call_random!(&mut app);
+        // the raw format is the next draw of the same input
+        let req =
+            test::TestRequest::with_uri("/random/bytes?round=6&length=100&format=raw").to_request();
+        let resp = test::call_service(&app, req).await;
         assert_eq!(
-            randomness,
-            "a0e68303b27400e78fd3170af2a5387f9a8fe291545f8461cafafd90fb0e7357"
+            resp.headers().get(http::header::CONTENT_TYPE).unwrap(),
+            "application/octet-stream"
         );
+        let raw_bytes = test::read_body(resp).await;
+
+        let seed = other_state.get_randomness_for_round(6, "").await.unwrap();
+        assert_eq!(raw_bytes, expand_seed(&seed, 100));
+
+        let req = test::TestRequest::with_uri("/random/bytes?round=6&length=1000000").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::with_uri("/random/bytes?round=6").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
     }
 
     #[actix_web::test]
-    async fn test_request_finish_with_beacon_inside_input_scenario_2() {
+    async fn request_random_range_and_shuffle_by_round() {
         check_if_dotenv_is_loaded!();
-        let empty = mock_factory(None).unwrap();
+        mock_rollup_server!(status_code(202));
 
         let beacon = DrandBeacon::builder()
-            .with_randomness("7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string())
-            .with_round(2832127)
-            .with_signature("8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352".to_string())
-            .build()
-            .wrap();
+            .with_round(6)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        let app_state = web::Data::new(AppState::with_journal(None));
+        app_state.keep_beacon(beacon.clone()).await;
+        let other_state = web::Data::new(AppState::with_journal(None));
+        other_state.keep_beacon(beacon).await;
 
-        let beacon = mock_factory(Some(beacon)).unwrap();
+        let logger = generate_log();
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::request_random_range)
+            .service(routes::request_random_shuffle);
 
-        mock_rollup_server!(responders::cycle![
-            json_encoded(empty),
-            json_encoded(beacon)
-        ]);
+        let app = test::init_service(app).await;
 
-        let app_state = web::Data::new(AppState::new());
-        let logger = generate_log();
+        let req =
+            test::TestRequest::with_uri("/random/range?round=6&min=1&max=52&count=5").to_request();
+        let numbers: Vec<u64> = test::call_and_read_body_json(&app, req).await;
+        let seed = other_state.get_randomness_for_round(6, "").await.unwrap();
+        assert_eq!(numbers, sample_range(&seed, 1, 52, 5));
+
+        let req = test::TestRequest::with_uri("/random/shuffle?round=6&n=52").to_request();
+        let deck: Vec<u64> = test::call_and_read_body_json(&app, req).await;
+        let seed = other_state.get_randomness_for_round(6, "").await.unwrap();
+        assert_eq!(deck, shuffle(&seed, 52));
+
+        let req = test::TestRequest::with_uri("/random/range?round=6&min=2&max=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let req = test::TestRequest::with_uri("/random/shuffle?round=6&n=100000").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn request_random_batch() {
+        check_if_dotenv_is_loaded!();
+        mock_rollup_server!(status_code(202));
+
+        let beacon = DrandBeacon::builder()
+            .with_round(6)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        let app_state = web::Data::new(AppState::with_journal(None));
+        app_state.keep_beacon(beacon.clone()).await;
+        let manager = app_state.input_buffer_manager.clone();
+        let genesis = app_state.timestamp_for_round(0).unwrap();
+        let other_state = web::Data::new(AppState::with_journal(None));
+        other_state.keep_beacon(beacon).await;
 
+        let logger = generate_log();
         let app = App::new()
             .wrap(logger)
             .app_data(app_state.clone())
-            .service(routes::consume_buffer)
-            .service(routes::request_random);
+            .service(routes::request_random_batch);
 
-        let mut app = test::init_service(app).await;
-
-        // the DApp call our middleware to start something
-        let req = call_finish!(&mut app);
-        assert_eq!(req["request_type"], "advance_state");
+        let app = test::init_service(app).await;
 
-        // the DApp call our /random inside middleware to get a seed to generate a random number
-        let randomness = call_random!(&mut app);
+        let req = test::TestRequest::post()
+            .uri("/random/batch")
+            .set_json(json!([
+                { "round": 6, "count": 2 },
+                { "timestamp": genesis + 10, "domain": "cards" }
+            ]))
+            .to_request();
+        let batch: Vec<Vec<String>> = test::call_and_read_body_json(&app, req).await;
+
+        let expected: Vec<Vec<String>> = vec![
+            vec![
+                hex::encode(other_state.get_randomness_for_round(6, "").await.unwrap()),
+                hex::encode(other_state.get_randomness_for_round(6, "").await.unwrap()),
+            ],
+            vec![hex::encode(
+                other_state
+                    .get_randomness_for_timestamp(genesis + 10, "cards")
+                    .await
+                    .unwrap(),
+            )],
+        ];
+        assert_eq!(batch, expected);
+
+        // rounds 7 and 8 did not arrive yet, so nothing is drawn and the oldest beacon is pending
+        let req = test::TestRequest::post()
+            .uri("/random/batch")
+            .set_json(json!([{ "round": 6 }, { "round": 8 }, { "round": 7 }]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(
+            manager.lock().await.pending_beacon_timestamp.get(),
+            genesis + 18
+        );
 
-        // check randomness
+        let req = test::TestRequest::post()
+            .uri("/random/batch")
+            .set_json(json!([{ "round": 6 }]))
+            .to_request();
+        let batch: Vec<Vec<String>> = test::call_and_read_body_json(&app, req).await;
         assert_eq!(
-            randomness,
-            "a0e68303b27400e78fd3170af2a5387f9a8fe291545f8461cafafd90fb0e7357"
+            batch,
+            vec![vec![hex::encode(
+                other_state.get_randomness_for_round(6, "").await.unwrap()
+            )]]
         );
+
+        let req = test::TestRequest::post()
+            .uri("/random/batch")
+            .set_json(json!([{ "round": 6, "timestamp": genesis }]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
     }
 
     #[actix_web::test]
-    async fn test_get_drand_beacon() {
-        generate_log();
+    async fn request_random_batch_across_future_rounds() {
         check_if_dotenv_is_loaded!();
-        let payload = generate_payload_hex(
-            json!({"beacon":{"round":2797373,"randomness":"a8482088c159d7a5c9a54cf599c686febbef00d97d0c460dfee3cd80ff371dd9","signature":"857325b9d4d9e81b32ff98f0da6fc2c6af02b10207ef148d2d39b82b77159d766a9edf8a68a793135890a7dfa66166a7"}}),
-        ).unwrap();
-        let beacon = get_drand_beacon(&payload).ok();
-        assert!(beacon.is_some());
 
-        let payload = generate_payload_hex(
-            json!({"beacon":{"round":4088012,"randomness":"9f020c15bbee97470e2cebe5f600b66cccf600b6c01491755ffaef893ea73009","signature":"b75a01a468f49abde3b5c81c0713d819845d113bb5a66bd3a576fe40b10927271d9d25c1c1bbf66b73e7b3c2b6399648"}}),
-        ).unwrap();
-        let beacon = get_drand_beacon(&payload).ok();
-        assert!(beacon.is_none());
+        // quicknet rounds 123 and 2797373, the provider sends the pending one each time
+        let oldest = mock_factory(Some(json!({"beacon":{"round":123,"randomness":"","signature":"b75c69d0b72a5d906e854e808ba7e2accb1542ac355ae486d591aa9d43765482e26cd02df835d3546d23c4b13e0dfc92"}}))).unwrap();
+        let newest = mock_factory(Some(json!({"beacon":{"round":2797373,"randomness":"","signature":"857325b9d4d9e81b32ff98f0da6fc2c6af02b10207ef148d2d39b82b77159d766a9edf8a68a793135890a7dfa66166a7"}}))).unwrap();
+        mock_rollup_server!(responders::cycle![
+            json_encoded(oldest),
+            json_encoded(newest)
+        ]);
 
-        let payload = generate_payload_hex(
-            json!({"beacon":{"round":4088011,"randomness":"9f020c15bbee97470e2cebe5f600b66cccf600b6c01491755ffaef893ea73009","signature":"b75a01a468f49abde3b5c81c0713d819845d113bb5a66bd3a576fe40b10927271d9d25c1c1bbf66b73e7b3c2b63996483333"}}),
-        ).unwrap();
-        let beacon = get_drand_beacon(&payload).ok();
-        assert!(beacon.is_none());
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::request_random_batch);
+        let app = test::init_service(app).await;
+
+        // the DApp is held for round 123 first, then for round 2797373
+        let req = test::TestRequest::post()
+            .uri("/random/batch")
+            .set_json(json!([{ "round": 2797373 }, { "round": 123 }]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let batch: Vec<Vec<String>> = test::read_body_json(resp).await;
+        assert_eq!(batch.len(), 2);
+
+        let manager = app_state.input_buffer_manager.lock().await;
+        let rounds: Vec<u64> = manager
+            .beacons
+            .values()
+            .map(|beacon| beacon.round)
+            .collect();
+        assert_eq!(rounds, vec![123, 2797373]);
+        assert_eq!(manager.state, LifecycleState::Draining);
+    }
+
+    #[actix_web::test]
+    async fn test_request_finish_without_input_to_respond() {
+        check_if_dotenv_is_loaded!();
+        mock_rollup_server!(status_code(202));
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+
+        let logger = generate_log();
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer);
+
+        let app = test::init_service(app).await;
+
+        // the DApp call our middleware
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+    }
+
+    #[actix_web::test]
+    async fn test_request_finish_with_input_to_respond() {
+        check_if_dotenv_is_loaded!();
+
+        let payload = mock_factory(None).unwrap();
+
+        mock_rollup_server!(json_encoded(payload));
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+
+        let logger = generate_log();
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer);
+
+        let mut app = test::init_service(app).await;
+
+        // the DApp call our middleware /finish
+        let req = call_finish!(&mut app);
+        assert_eq!(req["request_type"], "advance_state");
+    }
+
+    #[actix_web::test]
+    async fn test_forward_notice_to_rollup_server() {
+        check_if_dotenv_is_loaded!();
+
+        let payload = mock_factory(None).unwrap();
+
+        let server = SERVER.get_server();
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(1)
+            .respond_with(json_encoded(payload)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/notice",
+            ))
+            .times(1)
+            .respond_with(json_encoded(json!({"index": 3}))),
+        );
+        bind_rollup_server!(server);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+
+        let logger = generate_log();
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::notice);
+
+        let mut app = test::init_service(app).await;
+
+        let req = call_finish!(&mut app);
+        assert_eq!(req["request_type"], "advance_state");
+        let owner = app_state.output_owner().await;
+        assert_eq!(owner.input_index, Some(0));
+        assert!(!owner.buffered);
+
+        // the DApp sends the notice to our middleware
+        let req = test::TestRequest::with_uri("/notice")
+            .method(Method::POST)
+            .set_json(json!({"payload": "0x00"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body = test::read_body(resp).await;
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({"index": 3}));
+    }
+
+    #[actix_web::test]
+    async fn test_output_of_held_input_is_reported_with_its_input() {
+        check_if_dotenv_is_loaded!();
+
+        let input = mock_factory(None).unwrap();
+        let mut next_input = mock_factory(Some(json!({ "input": "0x01" }))).unwrap();
+        next_input["data"]["metadata"]["input_index"] = json!(1);
+
+        let server = SERVER.get_server();
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(2)
+            .respond_with(responders::cycle![
+                json_encoded(input),
+                json_encoded(next_input)
+            ]),
+        );
+        let notice = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let sent = notice.clone();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path(hyper::Method::POST.as_str(), "/notice"),
+                request::body(move |body: &[u8]| {
+                    *sent.lock().unwrap() = serde_json::from_slice::<serde_json::Value>(body).ok();
+                    true
+                }),
+            ])
+            .times(1)
+            .respond_with(json_encoded(json!({"index": 0}))),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/voucher",
+            ))
+            .times(1)
+            .respond_with(json_encoded(json!({"index": 1}))),
+        );
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let sent = reports.clone();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path(hyper::Method::POST.as_str(), "/report"),
+                request::body(move |body: &[u8]| {
+                    let body: serde_json::Value = serde_json::from_slice(body).unwrap();
+                    let payload = body["payload"].as_str().unwrap().trim_start_matches("0x");
+                    let payload: serde_json::Value =
+                        serde_json::from_slice(&hex::decode(payload).unwrap()).unwrap();
+                    sent.lock().unwrap().push(payload);
+                    true
+                }),
+            ])
+            .times(2)
+            .respond_with(status_code(200)),
+        );
+        bind_rollup_server!(server);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::request_random)
+            .service(routes::notice)
+            .service(routes::voucher);
+        let mut app = test::init_service(app).await;
+
+        let req = call_finish!(&mut app);
+        assert_eq!(req["data"]["metadata"]["input_index"], 0);
+
+        // the beacon is missing, so the rollup server already sent the next input
+        let uri = format!("/random?timestamp={}", timestamp_before_round(2832127));
+        let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let owner = app_state.output_owner().await;
+        assert_eq!(owner.input_index, Some(0));
+        assert!(owner.buffered);
+
+        let req = test::TestRequest::with_uri("/notice")
+            .method(Method::POST)
+            .set_json(json!({"payload": "0x0102"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // the notice reaches the rollup server as the DApp sent it
+        let notice = notice.lock().unwrap().take().unwrap();
+        assert_eq!(notice, json!({"payload": "0x0102"}));
+
+        let req = test::TestRequest::with_uri("/voucher")
+            .method(Method::POST)
+            .set_json(json!({"destination": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266", "payload": "0x00"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        // and a report ties each output to the input that produced it
+        let reports = reports.lock().unwrap().clone();
+        assert_eq!(
+            reports,
+            vec![
+                json!({"held_input_output": {"kind": "notice", "input_index": 0, "index": 0}}),
+                json!({"held_input_output": {"kind": "voucher", "input_index": 0, "index": 1}}),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_input_reaches_the_dapp_as_the_node_sent_it() {
+        check_if_dotenv_is_loaded!();
+
+        // a newer node: lowercase or unchecked sender, hex numbers, block_timestamp and unknown fields
+        let sender = "0xF39FD6e51aad88F6F4ce6aB8827279cffFb92266";
+        assert!(sender.parse::<Address>().is_err());
+        let node_input = json!({"data":{"metadata":{"block_number":"0xf1","input_index":"0x1","msg_sender":sender,"block_timestamp":1689949250,"chain_id":"0x7a69","prev_randao":"0x01","extra":"kept"},"payload":generate_payload_hex(json!({"input":"0x01"})).unwrap()},"request_type":"advance_state"});
+        mock_rollup_server!(responders::cycle![
+            json_encoded(mock_factory(None).unwrap()),
+            json_encoded(node_input.clone()),
+            json_encoded(node_input.clone())
+        ]);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::request_random);
+        let mut app = test::init_service(app).await;
+
+        call_finish!(&mut app);
+
+        // the input fetched while the DApp waits a beacon is buffered as it was sent
+        let uri = format!("/random?timestamp={}", timestamp_before_round(2832127));
+        let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        app_state
+            .keep_beacon(
+                DrandBeacon::builder()
+                    .with_round(2832127)
+                    .with_randomness(
+                        "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527"
+                            .to_string(),
+                    )
+                    .build(),
+            )
+            .await;
+        assert_eq!(call_finish!(&mut app), node_input);
+
+        // and so is the input dispatched right away
+        assert_eq!(call_finish!(&mut app), node_input);
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_restores_from_journal() {
+        check_if_dotenv_is_loaded!();
+
+        let path = std::env::temp_dir().join("cartesi-drand-journal-test.json");
+        let _ = std::fs::remove_file(&path);
+
+        let input: RollupInput = serde_json::from_value(mock_factory(None).unwrap()).unwrap();
+        let genesis;
+        let first_draw;
+        {
+            let app_state = AppState::with_journal(Some(path.clone()));
+            genesis = app_state.timestamp_for_round(0).unwrap();
+            app_state.start_input(&input).await;
+            app_state
+                .keep_beacon(
+                    DrandBeacon::builder()
+                        .with_round(6)
+                        .with_randomness(
+                            "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527"
+                                .to_string(),
+                        )
+                        .build(),
+                )
+                .await;
+            first_draw = app_state.get_randomness_for_round(6, "").await.unwrap();
+            assert!(app_state.get_randomness_for_round(7, "").await.is_none());
+            app_state.store_input(&input).await;
+            app_state.store_input(&input).await;
+        }
+
+        // the process restarts
+        let app_state = AppState::with_journal(Some(path.clone()));
+        {
+            let manager = app_state.input_buffer_manager.lock().await;
+            assert_eq!(manager.messages.len(), 2);
+            assert_eq!(manager.last_beacon().unwrap().round, 6);
+            assert_eq!(manager.pending_beacon_timestamp.get(), genesis + 18);
+        }
+
+        // the draw sequence goes on after the restart,
+        // and the draw only appends its delta to the log
+        let snapshot = std::fs::read(&path).unwrap();
+        let second_draw = app_state.get_randomness_for_round(6, "").await.unwrap();
+        assert_ne!(first_draw, second_draw);
+        assert_eq!(std::fs::read(&path).unwrap(), snapshot);
+        let log = std::fs::read_to_string(journal_log_path(&path)).unwrap();
+        assert_eq!(log.lines().count(), 1);
+
+        let in_memory = AppState::with_journal(None);
+        in_memory
+            .keep_beacon(
+                DrandBeacon::builder()
+                    .with_round(6)
+                    .with_randomness(
+                        "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527"
+                            .to_string(),
+                    )
+                    .build(),
+            )
+            .await;
+        in_memory.start_input(&input).await;
+        in_memory.get_randomness_for_round(6, "").await;
+        assert_eq!(
+            in_memory.get_randomness_for_round(6, "").await,
+            Some(second_draw)
+        );
+
+        // a line cut by a crash is dropped, the deltas before it are kept
+        assert!(app_state.consume_input().await.is_some());
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(journal_log_path(&path))
+            .unwrap();
+        std::io::Write::write_all(&mut log, b"{\"sequence\":").unwrap();
+        let restarted = AppState::with_journal(Some(path.clone()));
+        {
+            let manager = restarted.input_buffer_manager.lock().await;
+            assert_eq!(manager.messages.len(), 1);
+            assert_eq!(manager.advance_cursor.sequence, 2);
+        }
+        assert_eq!(
+            restarted.get_randomness_for_round(6, "").await,
+            in_memory.get_randomness_for_round(6, "").await
+        );
+
+        std::fs::remove_file(journal_log_path(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_refuses_an_unreadable_journal() {
+        check_if_dotenv_is_loaded!();
+
+        let path = std::env::temp_dir().join("cartesi-drand-unreadable-journal-test.json");
+        std::fs::write(&path, b"{\"messages\":[").unwrap();
+        let mut config = Config::from_env().unwrap();
+        config.journal_path = path.clone();
+
+        let error = AppState::new(&config).err().unwrap();
+        assert!(matches!(error, ConfigError::Journal { .. }));
+        // the journal is left for the operator to look at
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"messages\":[");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_request_finish_with_beacon_inside_input() {
+        let empty = mock_factory(None).unwrap();
+
+        let randomness =
+            String::from("7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527");
+        let signature = String::from("8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352");
+
+        let beacon = DrandBeacon::builder()
+            .with_randomness(randomness)
+            .with_round(2832127)
+            .with_signature(signature)
+            .build()
+            .wrap();
+
+        let beacon = mock_factory(Some(beacon)).unwrap();
+
+        check_if_dotenv_is_loaded!();
+        mock_rollup_server!(responders::cycle![
+            json_encoded(empty),
+            json_encoded(beacon)
+        ]);
+
+        let logger = generate_log();
+        let app_state = web::Data::new(AppState::with_journal(None));
+
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::request_random);
+
+        let mut app = test::init_service(app).await;
+
+        // the DApp call our middleware to start something
+        let req = call_finish!(&mut app);
+        assert_eq!(req["request_type"], "advance_state");
+
+        // call again and the beacon arrives, the DApp never sees an input with only the beacon
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+
+        // check randomness
+        let randomness = call_random!(&mut app);
+        assert_eq!(
+            randomness,
+            "e06a50a8d75628dfbb0cbe20c0ba90c66ef194acf5c6dea8096cfaa93d1b6784"
+        );
+
+        // the next draw of the same input moves the sequence
+        let randomness = call_random!(&mut app);
+        assert_eq!(
+            randomness,
+            "2c17b3e26d218698b3c32203282b83848b32ec9608f7117ddbcf75a422c22916"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_request_finish_with_beacon_inside_input_scenario_2() {
+        check_if_dotenv_is_loaded!();
+        let empty = mock_factory(None).unwrap();
+
+        let beacon = DrandBeacon::builder()
+            .with_randomness("7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string())
+            .with_round(2832127)
+            .with_signature("8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352".to_string())
+            .build()
+            .wrap();
+
+        let beacon = mock_factory(Some(beacon)).unwrap();
+
+        mock_rollup_server!(responders::cycle![
+            json_encoded(empty),
+            json_encoded(beacon)
+        ]);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let logger = generate_log();
+
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::request_random);
+
+        let mut app = test::init_service(app).await;
+
+        // the DApp call our middleware to start something
+        let req = call_finish!(&mut app);
+        assert_eq!(req["request_type"], "advance_state");
+
+        // the DApp call our /random inside middleware to get a seed to generate a random number
+        let randomness = call_random!(&mut app);
+
+        // check randomness
+        assert_eq!(
+            randomness,
+            "e06a50a8d75628dfbb0cbe20c0ba90c66ef194acf5c6dea8096cfaa93d1b6784"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_request_finish_holds_inputs_until_beacon() {
+        check_if_dotenv_is_loaded!();
+        let empty = mock_factory(None).unwrap();
+        let next_input = mock_factory(Some(json!({ "input": "0x01" }))).unwrap();
+
+        let beacon = DrandBeacon::builder()
+            .with_randomness("7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string())
+            .with_round(2832127)
+            .with_signature("8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352".to_string())
+            .build()
+            .wrap();
+        let beacon = mock_factory(Some(beacon)).unwrap();
+
+        mock_rollup_server!(responders::cycle![
+            json_encoded(empty.clone()),
+            json_encoded(next_input.clone()),
+            json_encoded(beacon)
+        ]);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let manager = app_state.input_buffer_manager.clone();
+        let logger = generate_log();
+
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::request_random);
+
+        let mut app = test::init_service(app).await;
+
+        let req = call_finish!(&mut app);
+        assert_eq!(req["request_type"], "advance_state");
+        assert_eq!(manager.lock().await.state, LifecycleState::Idle);
+
+        // the beacon is not there yet, so the next input is held
+        let timestamp = timestamp_before_round(2832127);
+        let uri = format!("/random?timestamp={}", timestamp);
+        let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let safe_timestamp = timestamp + app_state.safe_seconds();
+        {
+            let manager = manager.lock().await;
+            assert_eq!(
+                manager.state,
+                LifecycleState::AwaitingBeacon {
+                    timestamp: safe_timestamp
+                }
+            );
+            assert_eq!(manager.messages.len(), 1);
+        }
+
+        // the beacon arrives and the held input is dispatched first
+        let req = call_finish!(&mut app);
+        assert_eq!(req["data"]["payload"], next_input["data"]["payload"]);
+        assert_eq!(req["data"]["metadata"], next_input["data"]["metadata"]);
+        {
+            let manager = manager.lock().await;
+            // the input carrying only the beacon was consumed by the middleware
+            assert!(manager.messages.is_empty());
+        }
+
+        let randomness = call_random!(&mut app);
+        assert_eq!(randomness.len(), 64);
+
+        // then the DApp goes on with the next input of the rollup server
+        let req = call_finish!(&mut app);
+        assert_eq!(req["data"]["payload"], empty["data"]["payload"]);
+        {
+            let manager = manager.lock().await;
+            assert_eq!(manager.state, LifecycleState::Idle);
+            assert!(manager.messages.is_empty());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_status_by_route_and_inspect() {
+        check_if_dotenv_is_loaded!();
+        let server = SERVER.get_server();
+        let inspect = json!({"data":{"payload": format!("0x{}", hex::encode("drandstatus"))},"request_type":"inspect_state"});
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(1)
+            .respond_with(json_encoded(inspect)),
+        );
+        let report = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let sent = report.clone();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path(hyper::Method::POST.as_str(), "/report"),
+                request::body(move |body: &[u8]| {
+                    *sent.lock().unwrap() = serde_json::from_slice::<serde_json::Value>(body).ok();
+                    true
+                }),
+            ])
+            .times(1)
+            .respond_with(status_code(200)),
+        );
+        bind_rollup_server!(server);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let beacon = DrandBeacon::builder()
+            .with_round(6)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon).await;
+        let timestamp = app_state.timestamp_for_round(6).unwrap();
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::middleware_status)
+            .service(routes::consume_buffer);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::with_uri("/status").to_request();
+        let status: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(status["version"], app_state.version);
+        assert_eq!(status["drand"]["DRAND_PERIOD"], 3);
+        assert_eq!(
+            status["last_beacon"],
+            json!({"round": 6, "timestamp": timestamp})
+        );
+        assert_eq!(status["pending_beacon_timestamp"], 0);
+        assert_eq!(status["buffered_inputs"], 0);
+        assert_eq!(status["state"], "Idle");
+        assert_eq!(status["inspecting"], false);
+
+        // the DApp never sees the drandstatus inspect, the middleware answers it with a report
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+
+        let report = report.lock().unwrap().take().unwrap();
+        let payload = report["payload"].as_str().unwrap().trim_start_matches("0x");
+        let payload: serde_json::Value =
+            serde_json::from_slice(&hex::decode(payload).unwrap()).unwrap();
+        assert_eq!(payload["drand_status"]["last_beacon"]["round"], 6);
+        assert_eq!(payload["drand_status"]["buffered_inputs"], 0);
+
+        // the DApp waits round 8
+        assert!(app_state.get_randomness_for_round(8, "").await.is_none());
+        assert!(app_state.hold().await);
+        let pending_timestamp = app_state.timestamp_for_round(7).unwrap();
+        let req = test::TestRequest::with_uri("/status").to_request();
+        let status: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(status["pending_beacon_timestamp"], pending_timestamp);
+        assert_eq!(
+            status["state"],
+            json!({"AwaitingBeacon": {"timestamp": pending_timestamp}})
+        );
+
+        // once the beacon ends the hold nothing is pending anymore
+        let beacon = DrandBeacon::builder()
+            .with_round(8)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon).await;
+        let req = test::TestRequest::with_uri("/status").to_request();
+        let status: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(status["pending_beacon_timestamp"], 0);
+        assert_eq!(status["state"], "Draining");
+    }
+
+    #[actix_web::test]
+    async fn test_pending_beacon_reports() {
+        check_if_dotenv_is_loaded!();
+        let server = SERVER.get_server();
+        let inspect = json!({"data":{"payload": format!("0x{}", hex::encode("pendingdrandbeacon"))},"request_type":"inspect_state"});
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(1)
+            .respond_with(json_encoded(inspect)),
+        );
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let sent = reports.clone();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path(hyper::Method::POST.as_str(), "/report"),
+                request::body(move |body: &[u8]| {
+                    let report = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+                    sent.lock().unwrap().push(report);
+                    true
+                }),
+            ])
+            .times(2)
+            .respond_with(status_code(200)),
+        );
+        bind_rollup_server!(server);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let beacon = DrandBeacon::builder()
+            .with_round(6)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon).await;
+
+        // the seed of this timestamp comes from a round after the safe seconds
+        let timestamp = app_state.timestamp_for_round(10).unwrap();
+        assert!(app_state
+            .get_randomness_for_timestamp(timestamp, "")
+            .await
+            .is_none());
+        let pending_timestamp = timestamp + app_state.safe_seconds();
+        let round = app_state.round_for_timestamp(pending_timestamp);
+        assert!(app_state.timestamp_for_round(round).unwrap() > pending_timestamp);
+        assert!(app_state.timestamp_for_round(round - 1).unwrap() <= pending_timestamp);
+        assert!(app_state.hold().await);
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer);
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+
+        let reports = reports.lock().unwrap().clone();
+        assert_eq!(reports.len(), 2);
+        // the first report keeps the hex timestamp read by the older providers
+        assert_eq!(reports[0]["payload"], format!("{pending_timestamp:#x}"));
+
+        let payload = reports[1]["payload"]
+            .as_str()
+            .unwrap()
+            .trim_start_matches("0x");
+        let payload: serde_json::Value =
+            serde_json::from_slice(&hex::decode(payload).unwrap()).unwrap();
+        let drand = app_state.drand_config();
+        assert_eq!(
+            payload,
+            json!({"pending_drand_beacon": {
+                "timestamp": pending_timestamp,
+                "round": round,
+                "chain_hash": null,
+                "public_key": drand.current_chain().public_key,
+                "last_round": 6,
+            }})
+        );
+
+        // the beacon releases the held inputs, so nothing is pending anymore
+        let beacon = DrandBeacon::builder()
+            .with_round(round)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon).await;
+        let pending = app_state.pending_beacon().await;
+        assert_eq!(pending.timestamp, 0);
+        assert_eq!(pending.round, None);
+    }
+
+    #[actix_web::test]
+    async fn test_beacon_only_input_report() {
+        check_if_dotenv_is_loaded!();
+        let quicknet = json!({"round":123,"randomness":"","signature":"b75c69d0b72a5d906e854e808ba7e2accb1542ac355ae486d591aa9d43765482e26cd02df835d3546d23c4b13e0dfc92"});
+        let invalid = json!({"round":4088012,"randomness":"","signature":"b75a01a468f49abde3b5c81c0713d819845d113bb5a66bd3a576fe40b10927271d9d25c1c1bbf66b73e7b3c2b6399648"});
+        let input = mock_factory(Some(json!({
+            "input": "0x00",
+            "beacons": [quicknet.clone(), quicknet.clone(), invalid],
+        })))
+        .unwrap();
+        let rollup_input: RollupInput = serde_json::from_value(input.clone()).unwrap();
+        assert!(!has_input_inside_input(&rollup_input));
+
+        // a beacon next to a real input still goes to the DApp
+        let with_input =
+            mock_factory(Some(json!({ "input": "0x01", "beacon": quicknet }))).unwrap();
+        let with_input: RollupInput = serde_json::from_value(with_input).unwrap();
+        assert!(has_input_inside_input(&with_input));
+
+        let server = SERVER.get_server();
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(1)
+            .respond_with(json_encoded(input)),
+        );
+        let report = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let sent = report.clone();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path(hyper::Method::POST.as_str(), "/report"),
+                request::body(move |body: &[u8]| {
+                    *sent.lock().unwrap() = serde_json::from_slice::<serde_json::Value>(body).ok();
+                    true
+                }),
+            ])
+            .times(1)
+            .respond_with(status_code(200)),
+        );
+        bind_rollup_server!(server);
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer);
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+        assert!(app_state
+            .input_buffer_manager
+            .lock()
+            .await
+            .messages
+            .is_empty());
+
+        let report = report.lock().unwrap().take().unwrap();
+        let payload = report["payload"].as_str().unwrap().trim_start_matches("0x");
+        let payload: serde_json::Value =
+            serde_json::from_slice(&hex::decode(payload).unwrap()).unwrap();
+        let beacons = &payload["drand_beacons"];
+        assert_eq!(beacons["input_index"], 0);
+        assert_eq!(beacons["kept"], json!([123]));
+        assert_eq!(beacons["ignored"], json!([123]));
+        assert_eq!(beacons["rejected"][0]["round"], 4088012);
+        assert_eq!(app_state.metrics.beacons_ignored.get(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        check_if_dotenv_is_loaded!();
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let timestamp = timestamp_before_round(1000);
+
+        // no beacon yet, so the DApp is held while two inputs are fetched
+        assert!(app_state
+            .get_randomness_for_timestamp(timestamp, "")
+            .await
+            .is_none());
+        assert!(app_state.hold().await);
+        app_state.fetched().await;
+        app_state.fetched().await;
+        let pending_timestamp = timestamp + app_state.safe_seconds();
+        assert!(app_state.render_metrics().await.contains(&format!(
+            "drand_pending_beacon_timestamp {pending_timestamp}\n"
+        )));
+
+        let beacon = DrandBeacon::builder()
+            .with_round(1000)
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon).await;
+        assert!(app_state
+            .get_randomness_for_timestamp(timestamp, "")
+            .await
+            .is_some());
+
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::metrics);
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::with_uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+
+        assert!(body.contains("# TYPE drand_holds_total counter\ndrand_holds_total 1\n"));
+        assert!(body.contains("drand_randomness_served_total 1\n"));
+        assert!(body.contains("drand_hold_inputs_bucket{le=\"1\"} 0\n"));
+        assert!(body.contains("drand_hold_inputs_bucket{le=\"2\"} 1\n"));
+        assert!(body.contains("drand_hold_inputs_sum 2\n"));
+        assert!(body.contains("drand_last_beacon_round 1000\n"));
+        assert!(body.contains("drand_buffered_inputs 0\n"));
+        // the beacon ended the hold
+        assert!(body.contains("drand_pending_beacon_timestamp 0\n"));
+    }
+
+    #[actix_web::test]
+    async fn test_get_drand_beacon() {
+        generate_log();
+        check_if_dotenv_is_loaded!();
+        let drand = Config::from_env().unwrap().drand;
+        let payload = generate_payload_hex(
+            json!({"beacon":{"round":2797373,"randomness":"a8482088c159d7a5c9a54cf599c686febbef00d97d0c460dfee3cd80ff371dd9","signature":"857325b9d4d9e81b32ff98f0da6fc2c6af02b10207ef148d2d39b82b77159d766a9edf8a68a793135890a7dfa66166a7"}}),
+        ).unwrap();
+        let beacon = get_drand_beacons(&drand, &payload).unwrap().remove(0).ok();
+        assert!(beacon.is_some());
+
+        let payload = generate_payload_hex(
+            json!({"beacon":{"round":4088012,"randomness":"9f020c15bbee97470e2cebe5f600b66cccf600b6c01491755ffaef893ea73009","signature":"b75a01a468f49abde3b5c81c0713d819845d113bb5a66bd3a576fe40b10927271d9d25c1c1bbf66b73e7b3c2b6399648"}}),
+        ).unwrap();
+        let beacon = get_drand_beacons(&drand, &payload).unwrap().remove(0).ok();
+        assert!(beacon.is_none());
+
+        let payload = generate_payload_hex(
+            json!({"beacon":{"round":4088011,"randomness":"9f020c15bbee97470e2cebe5f600b66cccf600b6c01491755ffaef893ea73009","signature":"b75a01a468f49abde3b5c81c0713d819845d113bb5a66bd3a576fe40b10927271d9d25c1c1bbf66b73e7b3c2b63996483333"}}),
+        ).unwrap();
+        let beacon = get_drand_beacons(&drand, &payload).unwrap().remove(0).ok();
+        assert!(beacon.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_config_from_flags() {
+        check_if_dotenv_is_loaded!();
+
+        let cli = Cli::try_parse_from([
+            "cartesi-drand",
+            "--bind-address",
+            "127.0.0.1",
+            "--port",
+            "9000",
+            "--log-format",
+            "json",
+            "--rollup-http-server-url",
+            "http://localhost:5004/",
+        ])
+        .unwrap();
+        let config = Config::from_cli(cli).unwrap();
+        assert_eq!(config.bind_address, "127.0.0.1");
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.rollup_url, "http://localhost:5004");
+        assert_eq!(config.drand.current_chain().period, 3);
+
+        let cli = Cli::try_parse_from(["cartesi-drand", "--port", "not-a-port"]);
+        assert!(cli.is_err());
+
+        // without a config file nor DRAND_PUBLIC_KEY there is no chain to verify beacons with
+        let cli =
+            Cli::try_parse_from(["cartesi-drand", "--config-path", "missing.config.json"]).unwrap();
+        let error = Config::from_cli(cli).unwrap_err();
+        assert!(matches!(error, ConfigError::Drand { .. }));
+        assert!(error.to_string().contains("DRAND_PUBLIC_KEY"));
+
+        // the journal goes next to the drand config file, wherever the middleware runs from
+        let dir = std::env::temp_dir().join("cartesi-drand-config-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("drand.config.json");
+        std::fs::copy("drand.config.json", &config_path).unwrap();
+        let config_path = config_path.to_str().unwrap();
+        let cli = Cli::try_parse_from(["cartesi-drand", "--config-path", config_path]).unwrap();
+        let config = Config::from_cli(cli).unwrap();
+        assert_eq!(config.journal_path, dir.join("drand.journal.json"));
+
+        let cli = Cli::try_parse_from([
+            "cartesi-drand",
+            "--config-path",
+            config_path,
+            "--journal-path",
+            "/var/lib/drand/journal.json",
+        ])
+        .unwrap();
+        let config = Config::from_cli(cli).unwrap();
+        assert_eq!(
+            config.journal_path,
+            std::path::PathBuf::from("/var/lib/drand/journal.json")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_get_drand_beacon_across_rotation() {
+        // mainnet until the genesis of quicknet, then quicknet
+        let drand = serde_json::from_value::<DrandEnv>(json!({
+            "DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31",
+            "DRAND_PERIOD": 30,
+            "DRAND_GENESIS_TIME": 1595431050,
+            "DRAND_SAFE_SECONDS": 5,
+            "DRAND_SCHEME": "pedersen-bls-chained",
+            "DRAND_ROTATIONS": [{
+                "DRAND_PUBLIC_KEY": "83cf0f2896adee7eb8b5f01fcad3912212c437e0073e911fb90022d3e760183c8c4b450b6a0a6c3ac6a5776a2d1064510d1fec758c921cc22b0e17e63aaf4bcb5ed66304de9cf809bd274ca73bab4af5a6e9c76a4bc09e76eae8991ef5ece45a",
+                "DRAND_PERIOD": 3,
+                "DRAND_GENESIS_TIME": 1692803367,
+                "DRAND_SCHEME": "bls-unchained-g1-rfc9380",
+                "DRAND_EFFECTIVE_TIME": 1692803367
+            }]
+        }))
+        .unwrap();
+        let drand = DrandConfig::from_env(&drand).unwrap();
+        assert_eq!(drand.chains.len(), 2);
+        assert_eq!(DrandConfig::from_env(&drand.to_env()).unwrap(), drand);
+
+        // round 72785 is valid on both chains, only the mainnet key signed it
+        let payload = generate_payload_hex(
+            json!({"beacon":{"round":72785,"randomness":"","signature":"82f5d3d2de4db19d40a6980e8aa37842a0e55d1df06bd68bddc8d60002e8e959eb9cfa368b3c1b77d18f02a54fe047b80f0989315f83b12a74fd8679c4f12aae86eaf6ab5690b34f1fddd50ee3cc6f6cdf59e95526d5a5d82aaa84fa6f181e42","previous_signature":"a609e19a03c2fcc559e8dae14900aaefe517cb55c840f6e69bc8e4f66c8d18e8a609685d9917efbfb0c37f058c2de88f13d297c7e19e0ab24813079efe57a182554ff054c7638153f9b26a60e7111f71a0ff63d9571704905d3ca6df0b031747"}}),
+        ).unwrap();
+        let (beacon, timestamp) = get_drand_beacons(&drand, &payload)
+            .unwrap()
+            .remove(0)
+            .unwrap();
+        assert_eq!(beacon.round, 72785);
+        assert_eq!(timestamp, 1595431050 + 72785 * 30);
+
+        let payload = generate_payload_hex(
+            json!({"beacon":{"round":2797373,"randomness":"","signature":"857325b9d4d9e81b32ff98f0da6fc2c6af02b10207ef148d2d39b82b77159d766a9edf8a68a793135890a7dfa66166a7"}}),
+        ).unwrap();
+        let (beacon, timestamp) = get_drand_beacons(&drand, &payload)
+            .unwrap()
+            .remove(0)
+            .unwrap();
+        assert_eq!(beacon.round, 2797373);
+        assert_eq!(timestamp, 1692803367 + 2797373 * 3);
+
+        // the quicknet beacon is not trusted before the rotation
+        let mut mainnet = drand.clone();
+        mainnet.chains.pop();
+        assert!(get_drand_beacons(&mainnet, &payload)
+            .unwrap()
+            .remove(0)
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_get_drand_beacons_in_one_input() {
+        check_if_dotenv_is_loaded!();
+        let app_state = AppState::with_journal(None);
+        let payload = generate_payload_hex(json!({"beacons":[
+            {"round":2797373,"randomness":"","signature":"857325b9d4d9e81b32ff98f0da6fc2c6af02b10207ef148d2d39b82b77159d766a9edf8a68a793135890a7dfa66166a7"},
+            {"round":123,"randomness":"","signature":"b75c69d0b72a5d906e854e808ba7e2accb1542ac355ae486d591aa9d43765482e26cd02df835d3546d23c4b13e0dfc92"},
+            {"round":4088012,"randomness":"","signature":"b75a01a468f49abde3b5c81c0713d819845d113bb5a66bd3a576fe40b10927271d9d25c1c1bbf66b73e7b3c2b6399648"},
+        ]}))
+        .unwrap();
+        let results = get_drand_beacons(&app_state.drand_config(), &payload).unwrap();
+        let rounds: Vec<_> = results
+            .iter()
+            .map(|result| result.as_ref().map(|(beacon, _)| beacon.round))
+            .collect();
+        assert!(matches!(rounds[..], [Ok(2797373), Ok(123), Err(_)]));
+
+        // the single beacon form is still accepted
+        let payload = generate_payload_hex(json!({"beacon":{"round":123,"randomness":"","signature":"b75c69d0b72a5d906e854e808ba7e2accb1542ac355ae486d591aa9d43765482e26cd02df835d3546d23c4b13e0dfc92"}})).unwrap();
+        let single = get_drand_beacons(&app_state.drand_config(), &payload).unwrap();
+        assert!(matches!(single[..], [Ok((ref beacon, _))] if beacon.round == 123));
+        assert!(get_drand_beacons(&app_state.drand_config(), "0x7b7d").is_err());
+
+        // one input settles the held request with the newest beacon
+        let timestamp = timestamp_before_round(2797373);
+        assert!(app_state
+            .get_randomness_for_timestamp(timestamp, "")
+            .await
+            .is_none());
+        assert!(app_state.hold().await);
+        app_state
+            .keep_beacons(results.into_iter().flatten().collect())
+            .await;
+        assert_eq!(app_state.state().await, LifecycleState::Draining);
+        assert_eq!(app_state.input_buffer_manager.lock().await.beacons.len(), 2);
+        assert!(app_state
+            .get_randomness_for_timestamp(timestamp, "")
+            .await
+            .is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_verify_drand_beacon_for_each_scheme() {
+        // https://api3.drand.sh/52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971/public/123
+        let quicknet_key = "83cf0f2896adee7eb8b5f01fcad3912212c437e0073e911fb90022d3e760183c8c4b450b6a0a6c3ac6a5776a2d1064510d1fec758c921cc22b0e17e63aaf4bcb5ed66304de9cf809bd274ca73bab4af5a6e9c76a4bc09e76eae8991ef5ece45a";
+        let quicknet_beacon = DrandBeacon::builder()
+            .with_round(123)
+            .with_signature("b75c69d0b72a5d906e854e808ba7e2accb1542ac355ae486d591aa9d43765482e26cd02df835d3546d23c4b13e0dfc92".to_string())
+            .build();
+
+        // https://api3.drand.sh/dbd506d6ef76e5f386f41c651dcb808c5bcbd75471cc4eafa3f4df7ad4e4c493/public/1
+        let fastnet_key = "a0b862a7527fee3a731bcb59280ab6abd62d5c0b6ea03dc4ddf6612fdfc9d01f01c31542541771903475eb1ec6615f8d0df0b8b6dce385811d6dcf8cbefb8759e5e616a3dfd054c928940766d9a5b9db91e3b697e5d70a975181e007f87fca5e";
+        let fastnet_beacon = DrandBeacon::builder()
+            .with_round(1)
+            .with_signature("9544ddce2fdbe8688d6f5b4f98eed5d63eee3902e7e162050ac0f45905a55657714880adabe3c3096b92767d886567d0".to_string())
+            .build();
+
+        // https://pl-us.testnet.drand.sh/7672797f548f3f4748ac4bf3352fc6c6b6468c9ad40ad456a397545c6e2df5bf/public/223344
+        let unchained_key = "8200fc249deb0148eb918d6e213980c5d01acd7fc251900d9260136da3b54836ce125172399ddc69c4e3e11429b62c11";
+        let unchained_beacon = DrandBeacon::builder()
+            .with_round(223344)
+            .with_signature("94f6b85df7cce7237e8e7df66d794ddad092de5d8bb6a791b97e905aa89852e506ac36a792eba7021e22eebf34891f8914bf9a8dd9233ea0a4c5ca00ef8404999f899073dd2eade61fe54077fee8168f83dcb61a758b6883b38904054e64a433".to_string())
+            .build();
+
+        let beacon = verify_drand_beacon(
+            DrandScheme::BlsUnchainedG1Rfc9380,
+            quicknet_key,
+            &quicknet_beacon,
+        );
+        assert!(beacon.is_ok());
+
+        let beacon =
+            verify_drand_beacon(DrandScheme::BlsUnchainedOnG1, fastnet_key, &fastnet_beacon);
+        assert!(beacon.is_ok());
+
+        let beacon = verify_drand_beacon(
+            DrandScheme::PedersenBlsUnchained,
+            unchained_key,
+            &unchained_beacon,
+        );
+        assert!(beacon.is_ok());
+
+        // the same key with a different scheme does not verify
+        let beacon = verify_drand_beacon(
+            DrandScheme::BlsUnchainedOnG1,
+            quicknet_key,
+            &quicknet_beacon,
+        );
+        assert!(beacon.is_err());
+
+        let beacon = verify_drand_beacon(
+            DrandScheme::BlsUnchainedG1Rfc9380,
+            fastnet_key,
+            &fastnet_beacon,
+        );
+        assert!(beacon.is_err());
+
+        // a G1 key cannot be used with a G2 key scheme
+        let beacon = verify_drand_beacon(
+            DrandScheme::BlsUnchainedG1Rfc9380,
+            unchained_key,
+            &unchained_beacon,
+        );
+        assert!(beacon.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_drand_env_from_chain_info() {
+        // curl -sS https://api.drand.sh/52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971/info
+        let quicknet = json!({
+            "public_key": "83cf0f2896adee7eb8b5f01fcad3912212c437e0073e911fb90022d3e760183c8c4b450b6a0a6c3ac6a5776a2d1064510d1fec758c921cc22b0e17e63aaf4bcb5ed66304de9cf809bd274ca73bab4af5a6e9c76a4bc09e76eae8991ef5ece45a",
+            "period": 3,
+            "genesis_time": 1692803367,
+            "hash": "52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971",
+            "groupHash": "f477d5c89f21a17c863a7f937c6a6d15859414d2be09cd448d4279af331c5d3e",
+            "schemeID": "bls-unchained-g1-rfc9380",
+            "metadata": {"beaconID": "quicknet"}
+        });
+        let info = serde_json::from_value::<ChainInfo>(quicknet.clone()).unwrap();
+        let drand = drand_env_from_chain_info(&info).unwrap();
+        assert_eq!(drand.DRAND_PERIOD, Some(3));
+        assert_eq!(drand.DRAND_GENESIS_TIME, Some(1692803367));
+        assert_eq!(drand.DRAND_SCHEME, Some(DrandScheme::BlsUnchainedG1Rfc9380));
+        assert_eq!(drand.DRAND_SAFE_SECONDS, None);
+
+        // curl -sS https://api.drand.sh/info, the default chain created before the schemes
+        let mainnet = json!({
+            "public_key": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31",
+            "period": 30,
+            "genesis_time": 1595431050,
+            "hash": "8990e7a9aaed2ffed73dbd7092123d6f289930540d7651336225dc172e51b2ce",
+            "groupHash": "176f93498eac9ca337150b46d21dd58673ea4e3581185f869672e59fa4cb390a"
+        });
+        let info = serde_json::from_value::<ChainInfo>(mainnet).unwrap();
+        let drand = drand_env_from_chain_info(&info).unwrap();
+        assert_eq!(drand.DRAND_SCHEME, Some(DrandScheme::PedersenBlsChained));
+
+        let invalid_field = |info: serde_json::Value| {
+            let info = serde_json::from_value::<ChainInfo>(info).unwrap();
+            match drand_env_from_chain_info(&info) {
+                Err(CheckerError::InvalidDrandConfig { field, .. }) => field,
+                _ => panic!("expected an invalid config"),
+            }
+        };
+
+        let mut info = quicknet.clone();
+        info["genesis_time"] = json!(1692803368);
+        assert_eq!(invalid_field(info), "hash");
+
+        let mut info = quicknet.clone();
+        info["period"] = json!(0);
+        assert_eq!(invalid_field(info), "period");
+
+        let mut info = quicknet.clone();
+        info["schemeID"] = json!("bls-unknown");
+        assert_eq!(invalid_field(info), "schemeID");
+
+        // the hash matches, but the key is on G2 and the scheme expects G1
+        let mut info = quicknet.clone();
+        info["schemeID"] = json!("pedersen-bls-unchained");
+        assert_eq!(invalid_field(info), "public_key");
+    }
+
+    #[actix_web::test]
+    async fn test_update_drand_config_rejects_invalid_config() {
+        check_if_dotenv_is_loaded!();
+
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::update_drand_config);
+        let app = test::init_service(app).await;
+
+        // the beacons already kept cannot move to another chain
+        let last_beacon = app_state.timestamp_for_round(10).unwrap();
+        app_state
+            .keep_beacon(DrandBeacon::builder().with_round(10).build())
+            .await;
+
+        let configs = [
+            (json!({"DRAND_PUBLIC_KEY": "not hex"}), "public_key"),
+            (
+                json!({"DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31"}),
+                "public_key",
+            ),
+            (
+                json!({"DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31", "DRAND_SCHEME": "pedersen-bls-chained", "DRAND_PERIOD": 0}),
+                "DRAND_PERIOD",
+            ),
+            (
+                json!({"DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31", "DRAND_SCHEME": "pedersen-bls-chained", "DRAND_EFFECTIVE_TIME": last_beacon}),
+                "DRAND_EFFECTIVE_TIME",
+            ),
+        ];
+        for (config, field) in configs {
+            let req = test::TestRequest::with_uri("/update_drand_config")
+                .method(Method::PUT)
+                .set_json(config)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 400);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body["code"], "InvalidDrandConfig");
+            assert!(body["error"].as_str().unwrap().contains(field));
+            assert_eq!(body["field"], field);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_update_drand_config_from_owner_input() {
+        check_if_dotenv_is_loaded!();
+
+        // mainnet from a far future time on
+        let payload = mock_factory(Some(json!({"drand_config": {
+            "DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31",
+            "DRAND_PERIOD": 30,
+            "DRAND_GENESIS_TIME": 1595431050,
+            "DRAND_SCHEME": "pedersen-bls-chained",
+            "DRAND_EFFECTIVE_TIME": 4000000000u64
+        }})))
+        .unwrap();
+
+        let server = SERVER.get_server();
+        server.expect(
+            Expectation::matching(request::method_path(
+                hyper::Method::POST.as_str(),
+                "/finish",
+            ))
+            .times(2)
+            .respond_with(json_encoded(payload)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path(hyper::Method::POST.as_str(), "/report"),
+                request::body(|body: &[u8]| {
+                    let report = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+                    let payload = report["payload"].as_str().unwrap().trim_start_matches("0x");
+                    let payload = String::from_utf8(hex::decode(payload).unwrap()).unwrap();
+                    payload.contains("drand_config_rejected")
+                }),
+            ])
+            .times(1)
+            .respond_with(status_code(200)),
+        );
+        bind_rollup_server!(server);
+
+        let sender = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+            .parse::<Address>()
+            .unwrap();
+        let someone_else = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8"
+            .parse::<Address>()
+            .unwrap();
+
+        for (owner, chains) in [(sender, 2), (someone_else, 1)] {
+            let mut app_state = AppState::with_journal(None);
+            app_state.config_owner = Some(owner);
+            let app_state = web::Data::new(app_state);
+            let app = App::new()
+                .app_data(app_state.clone())
+                .service(routes::consume_buffer)
+                .service(routes::update_drand_config);
+            let app = test::init_service(app).await;
+
+            // the config input is not dispatched to the DApp
+            let req = test::TestRequest::with_uri("/finish")
+                .method(Method::POST)
+                .set_json(json!({"status": "accept"}))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 202);
+            assert_eq!(app_state.drand_config().chains.len(), chains);
+
+            // nor can anyone else update it through http
+            let req = test::TestRequest::with_uri("/update_drand_config")
+                .method(Method::PUT)
+                .set_json(json!({"DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31"}))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 403);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_drand_config_input_waits_for_held_inputs() {
+        check_if_dotenv_is_loaded!();
+
+        let input = mock_factory(None).unwrap();
+        let mut config_input = mock_factory(Some(json!({"drand_config": {
+            "DRAND_PUBLIC_KEY": "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31",
+            "DRAND_PERIOD": 30,
+            "DRAND_GENESIS_TIME": 1595431050,
+            "DRAND_SCHEME": "pedersen-bls-chained",
+            "DRAND_EFFECTIVE_TIME": 4000000000u64
+        }})))
+        .unwrap();
+        config_input["data"]["metadata"]["input_index"] = json!(1);
+        mock_rollup_server!(responders::cycle![
+            json_encoded(input),
+            json_encoded(config_input)
+        ]);
+
+        let mut app_state = AppState::with_journal(None);
+        app_state.config_owner = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().ok();
+        let app_state = web::Data::new(app_state);
+        let app = App::new()
+            .app_data(app_state.clone())
+            .service(routes::consume_buffer)
+            .service(routes::request_random);
+        let mut app = test::init_service(app).await;
+
+        let req = call_finish!(&mut app);
+        assert_eq!(req["data"]["metadata"]["input_index"], 0);
+
+        // the config arrives while the DApp waits a beacon for input 0
+        let uri = format!("/random?timestamp={}", timestamp_before_round(2832127));
+        let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(app_state.drand_config().chains.len(), 1);
+        assert_eq!(app_state.status().await.buffered_inputs, 1);
+
+        // input 0 still draws with the config it started with
+        app_state
+            .keep_beacon(
+                DrandBeacon::builder()
+                    .with_round(2832127)
+                    .with_randomness(
+                        "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527"
+                            .to_string(),
+                    )
+                    .build(),
+            )
+            .await;
+        let req = test::TestRequest::with_uri(uri.as_str()).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(app_state.drand_config().chains.len(), 1);
+
+        // and the config applies once input 0 is finished
+        let req = test::TestRequest::with_uri("/finish")
+            .method(Method::POST)
+            .set_json(json!({"status": "accept"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+        assert_eq!(app_state.drand_config().chains.len(), 2);
+        assert_eq!(app_state.status().await.buffered_inputs, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_verify_chained_drand_beacon() {
+        // curl -sS https://drand.cloudflare.com/info
+        let mainnet_key = "868f005eb8e6e4ca0a47c8a77ceaa5309a47978a7c71bc5cce96366b5d7a569937c529eeda66c7293784a9402801af31";
+
+        // curl -sS https://drand.cloudflare.com/public/72785
+        let signature = "82f5d3d2de4db19d40a6980e8aa37842a0e55d1df06bd68bddc8d60002e8e959eb9cfa368b3c1b77d18f02a54fe047b80f0989315f83b12a74fd8679c4f12aae86eaf6ab5690b34f1fddd50ee3cc6f6cdf59e95526d5a5d82aaa84fa6f181e42";
+        let previous_signature = "a609e19a03c2fcc559e8dae14900aaefe517cb55c840f6e69bc8e4f66c8d18e8a609685d9917efbfb0c37f058c2de88f13d297c7e19e0ab24813079efe57a182554ff054c7638153f9b26a60e7111f71a0ff63d9571704905d3ca6df0b031747";
+
+        let payload = json!({"beacon":{"round":72785,"randomness":"","signature":signature,"previous_signature":previous_signature}});
+        let payload = serde_json::from_value::<PayloadWithBeacon>(payload).unwrap();
+
+        let beacon = verify_drand_beacon(
+            DrandScheme::PedersenBlsChained,
+            mainnet_key,
+            &payload.beacon,
+        );
+        assert!(beacon.is_ok());
+
+        // the previous_signature is part of the signed message
+        let beacon = DrandBeacon::builder()
+            .with_round(72785)
+            .with_signature(signature.to_string())
+            .build();
+        let result = verify_drand_beacon(DrandScheme::PedersenBlsChained, mainnet_key, &beacon);
+        assert!(result.is_err());
+
+        let beacon = DrandBeacon::builder()
+            .with_round(72785)
+            .with_signature(signature.to_string())
+            .with_previous_signature(signature.to_string())
+            .build();
+        let result = verify_drand_beacon(DrandScheme::PedersenBlsChained, mainnet_key, &beacon);
+        assert!(result.is_err());
+
+        let result = verify_drand_beacon(
+            DrandScheme::PedersenBlsUnchained,
+            mainnet_key,
+            &payload.beacon,
+        );
+        assert!(result.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_derive_seed_from_public_data() {
+        // randomness of the quicknet round 2832127
+        let randomness = "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527";
+        let draw = Draw {
+            round: 2832127,
+            input_index: 0,
+            sequence: 0,
+            domain: "".to_string(),
+        };
+
+        let seed = derive_seed(DerivationScheme::Sha3_256, randomness, &draw).unwrap();
+        assert_eq!(
+            hex::encode(seed),
+            "e06a50a8d75628dfbb0cbe20c0ba90c66ef194acf5c6dea8096cfaa93d1b6784"
+        );
+
+        let draw = Draw {
+            domain: "cards".to_string(),
+            ..draw
+        };
+        let seed = derive_seed(DerivationScheme::Sha3_256, randomness, &draw).unwrap();
+        assert_eq!(
+            hex::encode(seed),
+            "1dc82f48b29c493f8c0dfb10b6dc40e78acd14cfbd0890e843c6942969ad9552"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_derive_seed_with_keccak256_packed() {
+        // keccak256(abi.encodePacked(bytes32 randomness, uint64 round, uint64 inputIndex, uint64 sequence, string domain))
+        let randomness = "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527";
+        let vectors = [
+            (
+                0,
+                0,
+                "",
+                "e2e81d931c0543c5d612ad02a288acd401601e713ce4871254b0d88bb1d5fd19",
+            ),
+            (
+                0,
+                0,
+                "cards",
+                "e23bd278587397223cfe851285f862153c4f8252a925c0f423d6c9205edaae78",
+            ),
+            (
+                3,
+                1,
+                "cards",
+                "8e89491ddd04ebb97d9cf4cffc9a0988eff9823cf5f1529219666c0f37ad135e",
+            ),
+        ];
+
+        for (input_index, sequence, domain, expected) in vectors {
+            let draw = Draw {
+                round: 2832127,
+                input_index,
+                sequence,
+                domain: domain.to_string(),
+            };
+            let seed = derive_seed(DerivationScheme::Keccak256Packed, randomness, &draw).unwrap();
+            assert_eq!(hex::encode(seed), expected);
+        }
+    }
+
+    /**
+     * Encrypt like tlock, using the signature of the round instead of the identity:
+     * e(signature, rP) = e(Q_id, master)^r
+     */
+    fn tlock_encrypt(signature: &str, sigma: &[u8], message: &[u8]) -> Vec<u8> {
+        let signature: [u8; 48] = hex::decode(signature).unwrap().try_into().unwrap();
+        let signature = G1Affine::from_compressed(&signature).unwrap();
+
+        let r = tlock::h3(sigma, message).unwrap();
+        let u = G2Affine::from(G2Projective::generator() * r);
+        let r_gid = pairing(&signature, &u);
+        let v: Vec<u8> = sigma
+            .iter()
+            .zip(tlock::gt_to_hash(&r_gid, sigma.len()).unwrap())
+            .map(|(a, b)| a ^ b)
+            .collect();
+        let w: Vec<u8> = message
+            .iter()
+            .zip(tlock::h4(sigma, message.len()))
+            .map(|(a, b)| a ^ b)
+            .collect();
+
+        [u.to_compressed().to_vec(), v, w].concat()
+    }
+
+    #[actix_web::test]
+    async fn test_tlock_gt_serialization() {
+        // the coefficients go from the last to the first, so the one of Gt lands on the last byte
+        let bytes = tlock::gt_to_bytes(&Gt::identity()).unwrap();
+        assert_eq!(bytes.len(), 576);
+        assert_eq!(bytes[575], 1);
+        assert!(bytes[..575].iter().all(|b| *b == 0));
+
+        // any other text is an error, never a panic
+        let display = Gt::identity().to_string();
+        for other in [
+            display.replacen("0x", "0x0", 1),
+            display.replacen(" + 0x", " + ", 1),
+            display.replacen("*v^2", "*v^3", 1),
+            display.replacen("Gt(", "Gt2(", 1),
+            display.replacen(")*w)", ")*x)", 1),
+            String::new(),
+        ] {
+            assert!(matches!(
+                tlock::gt_bytes_from_display(&other),
+                Err(CheckerError::TlockGtEncoding { .. })
+            ));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_tlock_decrypt_with_beacon_signature() {
+        // quicknet round 2832127, signatures on G1
+        let signature = "8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352";
+        let sigma = [7u8; 14];
+        let ciphertext = tlock_encrypt(signature, &sigma, b"sealed bid: 42");
+        assert_eq!(ciphertext.len(), 96 + 2 * 14);
+
+        let message =
+            tlock::decrypt(DrandScheme::BlsUnchainedG1Rfc9380, signature, &ciphertext).unwrap();
+        assert_eq!(message, b"sealed bid: 42");
+
+        // the signature of another round does not open it
+        let other = "b44679b9a59af2ec876b1a6b1ad52ea9b1615fc3982b19576350f93447cb1125e342b73a8dd2bacbe47e4b6b63ed5e39";
+        let result = tlock::decrypt(DrandScheme::BlsUnchainedG1Rfc9380, other, &ciphertext);
+        assert!(result.is_err());
+
+        let mut tampered = ciphertext.clone();
+        tampered[96 + 14] ^= 1;
+        let result = tlock::decrypt(DrandScheme::BlsUnchainedG1Rfc9380, signature, &tampered);
+        assert!(result.is_err());
+
+        let result = tlock::decrypt(
+            DrandScheme::BlsUnchainedG1Rfc9380,
+            signature,
+            &ciphertext[1..],
+        );
+        assert!(result.is_err());
+
+        // a chained round is never a tlock target
+        let error = tlock::decrypt(DrandScheme::PedersenBlsChained, signature, &ciphertext)
+            .unwrap_err()
+            .downcast::<CheckerError>()
+            .unwrap();
+        assert!(matches!(
+            *error,
+            CheckerError::TlockUnsupportedScheme { .. }
+        ));
+    }
+
+    #[actix_web::test]
+    async fn request_tlock_decrypt_by_round() {
+        check_if_dotenv_is_loaded!();
+        mock_rollup_server!(status_code(202));
+
+        let signature = "8f4c029827e0c1d6f5db875c1927bc79cb15188e046de5ad627cb7d1efce87b1f3de99a045b770632333a41af3abf352";
+        let app_state = web::Data::new(AppState::with_journal(None));
+        let beacon = DrandBeacon::builder()
+            .with_round(2832127)
+            .with_signature(signature.to_string())
+            .with_randomness(
+                "7ade997ac926a8cada6835a4a16dfb2d31e639c7ac4ea4b508d5d3829496b527".to_string(),
+            )
+            .build();
+        app_state.keep_beacon(beacon).await;
+
+        let logger = generate_log();
+        let app = App::new()
+            .wrap(logger)
+            .app_data(app_state.clone())
+            .service(routes::tlock_decrypt);
+
+        let app = test::init_service(app).await;
+
+        let ciphertext = tlock_encrypt(signature, &[9u8; 32], &[1u8; 32]);
+        let req = test::TestRequest::with_uri("/tlock/decrypt")
+            .method(Method::POST)
+            .set_json(json!({"round": 2832127, "ciphertext": hex::encode(&ciphertext)}))
+            .to_request();
+        let resp = test::call_and_read_body(&app, req).await;
+        assert_eq!(std::str::from_utf8(&resp).unwrap(), hex::encode([1u8; 32]));
+
+        // a newer beacon is known, so the DApp is held until the provider sends this round too
+        let req = test::TestRequest::with_uri("/tlock/decrypt")
+            .method(Method::POST)
+            .set_json(json!({"round": 2832126, "ciphertext": hex::encode(&ciphertext)}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        assert_eq!(
+            app_state.state().await,
+            LifecycleState::AwaitingBeacon {
+                timestamp: app_state.timestamp_for_round(2832125).unwrap()
+            }
+        );
+
+        let req = test::TestRequest::with_uri("/tlock/decrypt")
+            .method(Method::POST)
+            .set_json(json!({"round": 2832127, "ciphertext": "zz"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_expand_seed_with_shake256() {
+        let seed = hex!("e06a50a8d75628dfbb0cbe20c0ba90c66ef194acf5c6dea8096cfaa93d1b6784");
+
+        let bytes = expand_seed(&seed, 48);
+        assert_eq!(
+            hex::encode(&bytes),
+            "e2c6da677d460b1cc385e49844f0376170e31034bd66e6a2de589bb93acb3cb726a16bb3dba15baea5e9dde05e26f4e6"
+        );
+
+        // shorter streams are a prefix of longer ones
+        assert_eq!(expand_seed(&seed, 16), bytes[..16]);
+        assert!(expand_seed(&seed, 0).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_sample_range_and_shuffle() {
+        let seed = hex!("e06a50a8d75628dfbb0cbe20c0ba90c66ef194acf5c6dea8096cfaa93d1b6784");
+
+        assert_eq!(
+            sample_range(&seed, 1, 6, 10),
+            vec![5, 2, 5, 6, 3, 1, 5, 5, 3, 3]
+        );
+        assert_eq!(
+            sample_range(&seed, 0, u64::MAX, 2),
+            vec![16340988435978652444, 14088918351941220193]
+        );
+        // half of the u64 space is outside the zone, the first two values are rejected
+        assert_eq!(
+            sample_range(&seed, 0, 1 << 63, 3),
+            vec![
+                8134363170663229090,
+                2783624464919780270,
+                1275152613396996958
+            ]
+        );
+        assert_eq!(sample_range(&seed, 7, 7, 3), vec![7, 7, 7]);
+
+        assert_eq!(shuffle(&seed, 10), vec![3, 0, 6, 5, 9, 8, 1, 2, 7, 4]);
+        assert!(shuffle(&seed, 0).is_empty());
     }
 
-    // #[actix_web::test]
-    // async fn test_update_key() {
-    //     env_logger::builder().is_test(true).try_init().unwrap();
-    //     check_if_dotenv_is_loaded!();
-    //     let logger = Logger::default();
-
-    //     let app_state = web::Data::new(AppState::new());
-    //     let app = App::new()
-    //         .wrap(logger)
-    //         .app_data(app_state.clone())
-    //         .service(routes::update_drand_config);
-
-    //     let drand_env = serde_json::to_string(&DrandEnv {
-    //         DRAND_PUBLIC_KEY: "0x123".to_string(),
-    //         DRAND_PERIOD: None,
-    //         DRAND_GENESIS_TIME: None,
-    //         DRAND_SAFE_SECONDS: Some(1000),
-    //     })
-    //     .unwrap();
-
-    //     let req = test::TestRequest::default()
-    //         .set_json(drand_env)
-    //         .to_http_request();
-    // }
     #[actix_web::test]
     async fn test_verify_fast() {
         const PK_HEX3: [u8; 96] = hex!("a0b862a7527fee3a731bcb59280ab6abd62d5c0b6ea03dc4ddf6612fdfc9d01f01c31542541771903475eb1ec6615f8d0df0b8b6dce385811d6dcf8cbefb8759e5e616a3dfd054c928940766d9a5b9db91e3b697e5d70a975181e007f87fca5e");
@@ -0,0 +1,201 @@
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/**
+ * Upper bounds of the hold duration buckets, counted in inputs fetched while the DApp was held
+ */
+const HOLD_INPUT_BUCKETS: [u64; 7] = [0, 1, 2, 5, 10, 20, 50];
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Default)]
+pub struct Histogram {
+    buckets: [AtomicU64; HOLD_INPUT_BUCKETS.len()],
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, value: u64) {
+        for (bucket, bound) in self.buckets.iter().zip(HOLD_INPUT_BUCKETS) {
+            if value <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+    pub fn sum(&self) -> u64 {
+        self.sum.load(Ordering::Relaxed)
+    }
+}
+
+/**
+ * Counters of the middleware since it started, exported by /metrics in the Prometheus text format
+ */
+#[derive(Default)]
+pub struct Metrics {
+    pub beacons_accepted: Counter,
+    /**
+     * Inputs carrying a beacon that no drand chain could verify
+     */
+    pub beacons_rejected: Counter,
+    /**
+     * Verified beacons of rounds already known, or older than the whole beacon history
+     */
+    pub beacons_ignored: Counter,
+    pub randomness_served: Counter,
+    pub holds: Counter,
+    pub hold_inputs: Histogram,
+    pub finish_roundtrips: Counter,
+    buffered_inputs_max: AtomicU64,
+    /**
+     * Id of the last input fetched when the current hold started
+     */
+    hold_started_at: AtomicU64,
+}
+
+/**
+ * Values read from the middleware state when the metrics are rendered
+ */
+pub struct Gauges {
+    pub buffered_inputs: usize,
+    pub pending_beacon_timestamp: u64,
+    pub last_beacon_round: Option<u64>,
+}
+
+impl Metrics {
+    pub fn hold_started(&self, fetch_id: u64) {
+        self.holds.inc();
+        self.hold_started_at.store(fetch_id, Ordering::Relaxed);
+    }
+    /**
+     * The beacon released the DApp, fetch_id being the last input fetched while it was held
+     */
+    pub fn hold_released(&self, fetch_id: u64) {
+        let started_at = self.hold_started_at.load(Ordering::Relaxed);
+        self.hold_inputs
+            .observe(fetch_id.saturating_sub(started_at));
+    }
+    pub fn buffered(&self, buffered_inputs: usize) {
+        self.buffered_inputs_max
+            .fetch_max(buffered_inputs as u64, Ordering::Relaxed);
+    }
+
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+        write_metric(
+            &mut out,
+            "drand_beacons_accepted_total",
+            "counter",
+            "Beacons verified and kept by the middleware",
+            self.beacons_accepted.get(),
+        );
+        write_metric(
+            &mut out,
+            "drand_beacons_rejected_total",
+            "counter",
+            "Beacons whose signature no drand chain could verify",
+            self.beacons_rejected.get(),
+        );
+        write_metric(
+            &mut out,
+            "drand_beacons_ignored_total",
+            "counter",
+            "Duplicate or stale beacons left out of the beacon history",
+            self.beacons_ignored.get(),
+        );
+        write_metric(
+            &mut out,
+            "drand_randomness_served_total",
+            "counter",
+            "Seeds derived for the DApp",
+            self.randomness_served.get(),
+        );
+        write_metric(
+            &mut out,
+            "drand_holds_total",
+            "counter",
+            "Times the DApp was held until a newer beacon arrived",
+            self.holds.get(),
+        );
+
+        let name = "drand_hold_inputs";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Inputs fetched from the rollup server while the DApp was held"
+        );
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (bucket, bound) in self.hold_inputs.buckets.iter().zip(HOLD_INPUT_BUCKETS) {
+            let value = bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {value}");
+        }
+        let count = self.hold_inputs.count();
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(out, "{name}_sum {}", self.hold_inputs.sum());
+        let _ = writeln!(out, "{name}_count {count}");
+
+        write_metric(
+            &mut out,
+            "drand_buffered_inputs",
+            "gauge",
+            "Inputs held in the buffer of the middleware",
+            gauges.buffered_inputs as u64,
+        );
+        write_metric(
+            &mut out,
+            "drand_buffered_inputs_max",
+            "gauge",
+            "Most inputs held in the buffer at once",
+            self.buffered_inputs_max.load(Ordering::Relaxed),
+        );
+        write_metric(
+            &mut out,
+            "drand_pending_beacon_timestamp",
+            "gauge",
+            "Time of the beacon the DApp waits, 0 when it waits none",
+            gauges.pending_beacon_timestamp,
+        );
+        if let Some(round) = gauges.last_beacon_round {
+            write_metric(
+                &mut out,
+                "drand_last_beacon_round",
+                "gauge",
+                "Round of the newest known beacon",
+                round,
+            );
+        }
+        write_metric(
+            &mut out,
+            "drand_finish_roundtrips_total",
+            "counter",
+            "Finish requests sent to the rollup server",
+            self.finish_roundtrips.get(),
+        );
+        out
+    }
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
@@ -1,36 +1,478 @@
 pub mod structs {
-    use std::{borrow::BorrowMut, cell::Cell, collections::VecDeque, sync::Arc};
+    use std::{
+        borrow::BorrowMut,
+        cell::Cell,
+        collections::{BTreeMap, VecDeque},
+        error::Error,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::{Arc, RwLock},
+    };
 
-    use dotenvy::var;
-    use log::info;
+    use cartesi_rollup_metadata::Address;
+    use log::{error, info};
     use serde::{Deserialize, Serialize};
     use serde_json::json;
-    use sha3::{Digest, Sha3_256};
     use tokio::sync::Mutex;
 
-    use crate::rollup::input::RollupInput;
+    use crate::{
+        config::Config,
+        errors::{CheckerError, ConfigError},
+        metrics::{Gauges, Metrics},
+        randomness::derive_seed,
+        rollup::input::{RollupInput, RollupState},
+        utils::util::{append_journal, read_journal, read_journal_log, write_journal},
+    };
 
-    #[derive(serde::Deserialize, serde::Serialize)]
+    #[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
     #[allow(non_snake_case)]
     pub struct DrandEnv {
         pub DRAND_PUBLIC_KEY: String,
         pub DRAND_PERIOD: Option<u64>,
         pub DRAND_GENESIS_TIME: Option<u64>,
         pub DRAND_SAFE_SECONDS: Option<u64>,
+        pub DRAND_SCHEME: Option<DrandScheme>,
+        pub DRAND_CHAIN_HASH: Option<String>,
+        /**
+         * Timestamp from which the chain is trusted, the chain is used right away when missing
+         */
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub DRAND_EFFECTIVE_TIME: Option<u64>,
+        /**
+         * Chains scheduled to replace this one, persisted in drand.config.json
+         */
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub DRAND_ROTATIONS: Vec<DrandEnv>,
     }
 
-    #[derive(Serialize)]
+    /**
+     * Chain information published by drand at /info
+     *
+     * {"public_key":"83cf0f...","period":3,"genesis_time":1692803367,"hash":"52db9b...","groupHash":"f477d5...","schemeID":"bls-unchained-g1-rfc9380","metadata":{"beaconID":"quicknet"}}
+     */
+    #[derive(Deserialize, Serialize, Debug, Clone)]
+    pub struct ChainInfo {
+        pub public_key: String,
+        pub period: u64,
+        pub genesis_time: u64,
+        pub hash: String,
+        #[serde(rename = "groupHash")]
+        pub group_hash: String,
+        /**
+         * Missing on the chains created before the schemes, which are pedersen-bls-chained
+         */
+        #[serde(rename = "schemeID")]
+        pub scheme_id: Option<String>,
+        #[serde(default)]
+        pub metadata: ChainMetadata,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, Default)]
+    pub struct ChainMetadata {
+        #[serde(rename = "beaconID", default)]
+        pub beacon_id: String,
+    }
+
+    /**
+     * Drand chain trusted for the beacons from its effective time on
+     */
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct DrandChain {
+        pub public_key: String,
+        pub scheme: DrandScheme,
+        pub period: u64,
+        pub genesis_time: u64,
+        pub chain_hash: Option<String>,
+        pub effective_time: u64,
+    }
+
+    impl DrandChain {
+        /**
+         * This chain with the values sent in the update, the missing ones are kept
+         */
+        fn updated(&self, drand: &DrandEnv, effective_time: u64) -> DrandChain {
+            DrandChain {
+                public_key: drand.DRAND_PUBLIC_KEY.clone(),
+                scheme: drand.DRAND_SCHEME.unwrap_or(self.scheme),
+                period: drand.DRAND_PERIOD.unwrap_or(self.period),
+                genesis_time: drand.DRAND_GENESIS_TIME.unwrap_or(self.genesis_time),
+                chain_hash: drand.DRAND_CHAIN_HASH.clone(),
+                effective_time,
+            }
+        }
+        fn to_env(&self) -> DrandEnv {
+            DrandEnv {
+                DRAND_PUBLIC_KEY: self.public_key.clone(),
+                DRAND_PERIOD: Some(self.period),
+                DRAND_GENESIS_TIME: Some(self.genesis_time),
+                DRAND_SCHEME: Some(self.scheme),
+                DRAND_CHAIN_HASH: self.chain_hash.clone(),
+                DRAND_EFFECTIVE_TIME: Some(self.effective_time).filter(|time| *time > 0),
+                ..Default::default()
+            }
+        }
+        /**
+         * First round whose beacon time is after the timestamp
+         */
+        pub fn round_for_timestamp(&self, timestamp: u64) -> u64 {
+            match timestamp.checked_sub(self.genesis_time) {
+                Some(elapsed) => (elapsed / self.period.max(1)).saturating_add(1),
+                None => 0,
+            }
+        }
+        /**
+         * Beacon time of the round, None when it does not fit in a u64
+         */
+        pub fn timestamp_for_round(&self, round: u64) -> Option<u64> {
+            round
+                .checked_mul(self.period)?
+                .checked_add(self.genesis_time)
+        }
+    }
+
+    /**
+     * History of the drand chains, sorted by effective time, so the beacons
+     * on each side of a rotation are checked against the chain of their time
+     */
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct DrandConfig {
+        pub chains: Vec<DrandChain>,
+        pub safe_seconds: u64,
+    }
+
+    impl DrandConfig {
+        pub fn from_env(drand: &DrandEnv) -> Result<DrandConfig, String> {
+            if drand.DRAND_PUBLIC_KEY.is_empty() {
+                return Err("Missing DRAND_PUBLIC_KEY".to_string());
+            }
+            let chain = DrandChain {
+                public_key: drand.DRAND_PUBLIC_KEY.clone(),
+                scheme: drand.DRAND_SCHEME.unwrap_or_default(),
+                period: drand.DRAND_PERIOD.ok_or("Missing DRAND_PERIOD")?,
+                genesis_time: drand
+                    .DRAND_GENESIS_TIME
+                    .ok_or("Missing DRAND_GENESIS_TIME")?,
+                chain_hash: drand.DRAND_CHAIN_HASH.clone(),
+                effective_time: drand.DRAND_EFFECTIVE_TIME.unwrap_or(0),
+            };
+            let safe_seconds = drand
+                .DRAND_SAFE_SECONDS
+                .ok_or("Missing DRAND_SAFE_SECONDS")?;
+
+            let config = DrandConfig {
+                chains: vec![chain],
+                safe_seconds,
+            };
+            Ok(drand
+                .DRAND_ROTATIONS
+                .iter()
+                .fold(config, |config, rotation| config.updated(rotation)))
+        }
+        pub fn to_env(&self) -> DrandEnv {
+            let mut chains = self.chains.iter().map(DrandChain::to_env);
+            let mut drand = chains.next().unwrap_or_default();
+            drand.DRAND_SAFE_SECONDS = Some(self.safe_seconds);
+            drand.DRAND_ROTATIONS = chains.collect();
+            drand
+        }
+        /**
+         * Config after the update. Without an effective time the chain replaces the whole history,
+         * otherwise it takes over at that time, dropping the rotations scheduled from then on.
+         */
+        pub fn updated(&self, drand: &DrandEnv) -> DrandConfig {
+            let (mut chains, previous) = match drand.DRAND_EFFECTIVE_TIME {
+                Some(effective_time) => (
+                    self.chains
+                        .iter()
+                        .filter(|chain| chain.effective_time < effective_time)
+                        .cloned()
+                        .collect(),
+                    self.chain_at(effective_time),
+                ),
+                None => (vec![], self.current_chain()),
+            };
+            chains.push(previous.updated(drand, drand.DRAND_EFFECTIVE_TIME.unwrap_or(0)));
+
+            DrandConfig {
+                chains,
+                safe_seconds: drand.DRAND_SAFE_SECONDS.unwrap_or(self.safe_seconds),
+            }
+        }
+        /**
+         * Latest chain sent, which may still be waiting for its effective time
+         */
+        pub fn current_chain(&self) -> &DrandChain {
+            self.chains.last().expect("drand config without chain")
+        }
+        /**
+         * Chain trusted at the timestamp, the oldest one before any effective time
+         */
+        pub fn chain_at(&self, timestamp: u64) -> &DrandChain {
+            self.chains
+                .iter()
+                .rev()
+                .find(|chain| chain.effective_time <= timestamp)
+                .unwrap_or(&self.chains[0])
+        }
+        /**
+         * Chains whose round falls within their own effective window, newest chains first.
+         * Around a rotation the same round may be valid on both chains.
+         */
+        pub fn chains_for_round(&self, round: u64) -> impl Iterator<Item = &DrandChain> {
+            self.chains
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(move |(index, chain)| {
+                    let Some(time) = chain.timestamp_for_round(round) else {
+                        return false;
+                    };
+                    let started = *index == 0 || chain.effective_time <= time;
+                    let replaced = self
+                        .chains
+                        .get(index + 1)
+                        .is_some_and(|next| next.effective_time <= time);
+                    started && !replaced
+                })
+                .map(|(_, chain)| chain)
+        }
+        pub fn chain_for_round(&self, round: u64) -> Option<&DrandChain> {
+            self.chains_for_round(round).next()
+        }
+        pub fn round_for_timestamp(&self, timestamp: u64) -> u64 {
+            self.chain_at(timestamp).round_for_timestamp(timestamp)
+        }
+        pub fn timestamp_for_round(&self, round: u64) -> Option<u64> {
+            self.chain_for_round(round)
+                .unwrap_or(self.current_chain())
+                .timestamp_for_round(round)
+        }
+    }
+
+    /**
+     * Query of /update_drand_chain_info, scheduling the chain when the effective time is set
+     */
+    #[derive(Deserialize, Debug, Default)]
+    pub struct ChainRotationQuery {
+        pub effective_time: Option<u64>,
+    }
+
+    /**
+     * Signature schemes of the drand networks, named after the `schemeID` of the chain info.
+     * See https://drand.love/docs/specification/#cryptographic-specification
+     */
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum DrandScheme {
+        #[serde(rename = "pedersen-bls-chained")]
+        PedersenBlsChained,
+        #[serde(rename = "pedersen-bls-unchained")]
+        PedersenBlsUnchained,
+        #[serde(rename = "bls-unchained-on-g1")]
+        BlsUnchainedOnG1,
+        #[default]
+        #[serde(rename = "bls-unchained-g1-rfc9380")]
+        BlsUnchainedG1Rfc9380,
+    }
+
+    impl DrandScheme {
+        pub fn as_str(&self) -> &str {
+            match self {
+                DrandScheme::PedersenBlsChained => "pedersen-bls-chained",
+                DrandScheme::PedersenBlsUnchained => "pedersen-bls-unchained",
+                DrandScheme::BlsUnchainedOnG1 => "bls-unchained-on-g1",
+                DrandScheme::BlsUnchainedG1Rfc9380 => "bls-unchained-g1-rfc9380",
+            }
+        }
+    }
+
+    impl FromStr for DrandScheme {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "pedersen-bls-chained" => Ok(DrandScheme::PedersenBlsChained),
+                "pedersen-bls-unchained" => Ok(DrandScheme::PedersenBlsUnchained),
+                "bls-unchained-on-g1" => Ok(DrandScheme::BlsUnchainedOnG1),
+                "bls-unchained-g1-rfc9380" => Ok(DrandScheme::BlsUnchainedG1Rfc9380),
+                _ => Err(format!("Unsupported drand scheme {}", s)),
+            }
+        }
+    }
+
+    /**
+     * Hash used to derive the seed of a draw from the beacon randomness
+     */
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum DerivationScheme {
+        #[default]
+        #[serde(rename = "sha3-256")]
+        Sha3_256,
+        #[serde(rename = "keccak256-packed")]
+        Keccak256Packed,
+    }
+
+    impl FromStr for DerivationScheme {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "sha3-256" => Ok(DerivationScheme::Sha3_256),
+                "keccak256-packed" => Ok(DerivationScheme::Keccak256Packed),
+                _ => Err(format!("Unsupported derivation scheme {}", s)),
+            }
+        }
+    }
+
+    /**
+     * Notices with the provenance of the draws
+     *
+     * Off: no notice
+     * Draw: one notice per value served to the DApp
+     * Input: one notice per input with all its draws, sent when the DApp finishes it
+     */
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ProvenanceMode {
+        #[default]
+        Off,
+        Draw,
+        Input,
+    }
+
+    impl FromStr for ProvenanceMode {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "off" => Ok(ProvenanceMode::Off),
+                "draw" => Ok(ProvenanceMode::Draw),
+                "input" => Ok(ProvenanceMode::Input),
+                _ => Err(format!("Unsupported provenance mode {}", s)),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
     pub struct Item {
         pub request: String,
+        /**
+         * Which input fetched from the rollup server this is
+         */
+        #[serde(default)]
+        pub fetch_id: u64,
     }
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct RequestRollups {
-        status: String,
+        pub status: String,
+    }
+
+    impl RequestRollups {
+        pub fn is_reject(&self) -> bool {
+            self.status == "reject"
+        }
+    }
+
+    /**
+     * Status sent with the next finish to the rollup server
+     */
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct RollupStatus {
+        pub status: &'static str,
+        /**
+         * Input index of a held input rejected by the DApp after the rollup server already accepted it
+         */
+        pub held_reject: Option<u64>,
+    }
+
+    /**
+     * Input an output of the DApp belongs to
+     */
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct OutputOwner {
+        /**
+         * Advance input index, None while the DApp processes an inspect
+         */
+        pub input_index: Option<u64>,
+        /**
+         * The DApp processes a held input, the rollup server attributes its outputs to the last input we fetched
+         */
+        pub buffered: bool,
+    }
+
+    /**
+     * Lifecycle of the middleware between the DApp and the rollup server
+     *
+     * Idle: the DApp processes an advance input (or nothing), inputs flow straight through
+     * AwaitingBeacon: the DApp asked a beacon newer than timestamp, new inputs are held in the buffer
+     * Inspecting: the DApp processes an inspect, so we cannot finish to wait a beacon
+     * Draining: the beacon arrived, the held inputs are dispatched before the new ones
+     */
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum LifecycleState {
+        #[default]
+        Idle,
+        AwaitingBeacon {
+            timestamp: u64,
+        },
+        Inspecting,
+        Draining,
     }
 
-    pub struct Flag {
-        pub is_holding: bool,
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LifecycleEvent {
+        /**
+         * An input was handed to the DApp by /finish, buffered is how many inputs are still held
+         */
+        Dispatch { inspect: bool, buffered: usize },
+        /**
+         * /random needs a beacon newer than timestamp
+         */
+        Hold { timestamp: u64 },
+        /**
+         * A verified beacon of this timestamp was kept
+         */
+        Beacon { timestamp: u64 },
+    }
+
+    impl LifecycleState {
+        /**
+         * Next state after the event, None when the event is not allowed in this state
+         */
+        pub fn on(self, event: LifecycleEvent) -> Option<LifecycleState> {
+            use LifecycleEvent::*;
+            use LifecycleState::*;
+
+            match (self, event) {
+                // nothing reaches the DApp while we wait the beacon
+                (AwaitingBeacon { .. }, Dispatch { .. }) => None,
+                (_, Dispatch { inspect: true, .. }) => Some(Inspecting),
+                (_, Dispatch { buffered: 0, .. }) => Some(Idle),
+                (_, Dispatch { .. }) => Some(Draining),
+
+                // an inspect cannot change the rollup state, so it cannot wait a beacon
+                (Inspecting, Hold { .. }) => None,
+                (AwaitingBeacon { timestamp: current }, Hold { timestamp }) => {
+                    Some(AwaitingBeacon {
+                        timestamp: current.max(timestamp),
+                    })
+                }
+                (Idle | Draining, Hold { timestamp }) => Some(AwaitingBeacon { timestamp }),
+
+                (AwaitingBeacon { timestamp: pending }, Beacon { timestamp }) => {
+                    if pending < timestamp {
+                        Some(Draining)
+                    } else {
+                        Some(self)
+                    }
+                }
+                (Idle | Inspecting | Draining, Beacon { .. }) => Some(self),
+            }
+        }
+
+        pub fn is_holding(&self) -> bool {
+            matches!(self, LifecycleState::AwaitingBeacon { .. })
+        }
     }
 
     #[derive(Deserialize)]
@@ -38,11 +480,169 @@ pub mod structs {
         pub timestamp: u64,
     }
 
-    #[derive(Default)]
+    #[derive(Deserialize)]
+    pub struct Round {
+        pub round: u64,
+    }
+
+    /**
+     * Query of the /random endpoint, either by timestamp or by drand round
+     */
+    #[derive(Deserialize, Default)]
+    pub struct RandomQuery {
+        pub timestamp: Option<u64>,
+        pub round: Option<u64>,
+        #[serde(default)]
+        pub domain: String,
+    }
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ByteFormat {
+        #[default]
+        Hex,
+        Raw,
+    }
+
+    /**
+     * Query of the /random/bytes endpoint, sent together with the RandomQuery fields
+     */
+    #[derive(Deserialize)]
+    pub struct RandomBytesQuery {
+        pub length: usize,
+        #[serde(default)]
+        pub format: ByteFormat,
+    }
+
+    /**
+     * Query of the /random/range endpoint, sent together with the RandomQuery fields
+     */
+    #[derive(Deserialize)]
+    pub struct RangeQuery {
+        pub min: u64,
+        pub max: u64,
+        #[serde(default = "default_count")]
+        pub count: usize,
+    }
+
+    fn default_count() -> usize {
+        1
+    }
+
+    /**
+     * One entry of the /random/batch body, asking count draws for the query
+     */
+    #[derive(Deserialize)]
+    pub struct RandomSpec {
+        #[serde(flatten)]
+        pub query: RandomQuery,
+        #[serde(default = "default_count")]
+        pub count: usize,
+    }
+
+    /**
+     * Query of the /random/shuffle endpoint, sent together with the RandomQuery fields
+     */
+    #[derive(Deserialize)]
+    pub struct ShuffleQuery {
+        pub n: usize,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct TlockDecryptRequest {
+        pub round: u64,
+        /**
+         * Hex of U || V || W
+         */
+        pub ciphertext: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct RoundTimestamp {
+        pub round: u64,
+        pub timestamp: u64,
+    }
+
+    /**
+     * Beacon the DApp waits, answered by the pendingdrandbeacon inspect,
+     * so the providers don't need to compute the round from the drand config
+     */
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct PendingBeacon {
+        /**
+         * The beacon must be newer than this time, 0 when no input waits a beacon
+         */
+        pub timestamp: u64,
+        /**
+         * First round newer than the timestamp
+         */
+        pub round: Option<u64>,
+        pub chain_hash: Option<String>,
+        pub public_key: String,
+        /**
+         * Newest round already known by the middleware
+         */
+        pub last_round: Option<u64>,
+    }
+
+    /**
+     * What the middleware is doing, answered by /status and by the drandstatus inspect
+     */
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct MiddlewareStatus {
+        pub version: String,
+        pub drand: DrandEnv,
+        pub config_owner: Option<Address>,
+        pub last_beacon: Option<RoundTimestamp>,
+        pub pending_beacon_timestamp: u64,
+        pub buffered_inputs: usize,
+        pub state: LifecycleState,
+        pub inspecting: bool,
+    }
+
+    /**
+     * Public data that identifies a single draw
+     */
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+    pub struct Draw {
+        pub round: u64,
+        pub input_index: u64,
+        pub sequence: u64,
+        pub domain: String,
+    }
+
+    /**
+     * Position of the next draw inside the input being processed by the DApp
+     */
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct DrawCursor {
+        pub input_index: u64,
+        pub sequence: u64,
+    }
+
+    /**
+     * Everything needed to recompute a draw from public data
+     */
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Provenance {
+        pub round: u64,
+        pub signature: String,
+        pub timestamp: u64,
+        pub input_index: u64,
+        pub sequence: u64,
+        pub domain: String,
+        #[serde(default)]
+        pub derivation: DerivationScheme,
+        pub output: String,
+    }
+
+    #[derive(Default, Clone, Serialize, Deserialize)]
     pub struct Beacon {
         pub timestamp: u64,
         pub round: u64,
         pub randomness: String,
+        #[serde(default)]
+        pub signature: String,
     }
 
     #[derive(Default)]
@@ -73,6 +673,7 @@ pub mod structs {
         pub fn with_drand_beacon(mut self, drand_beacon: &DrandBeacon) -> BeaconBuilder {
             self.0.round = drand_beacon.round;
             self.0.randomness = drand_beacon.randomness.to_string();
+            self.0.signature = drand_beacon.signature.to_string();
             self
         }
 
@@ -86,10 +687,48 @@ pub mod structs {
         pub beacon: DrandBeacon,
     }
 
+    /**
+     * Answer to an input carrying only beacons, which the middleware consumes instead of the DApp
+     */
+    #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct BeaconInputReport {
+        pub input_index: Option<u64>,
+        /**
+         * Rounds added to the known beacons
+         */
+        pub kept: Vec<u64>,
+        /**
+         * Rounds already known, or older than the whole beacon history
+         */
+        pub ignored: Vec<u64>,
+        pub rejected: Vec<RejectedBeacon>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct RejectedBeacon {
+        pub round: u64,
+        pub cause: String,
+    }
+
+    /**
+     * Many beacons in one input, to catch up after a downtime with a single transaction
+     */
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct PayloadWithBeacons {
+        pub beacons: Vec<DrandBeacon>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct PayloadWithDrandConfig {
+        pub drand_config: DrandEnv,
+    }
+
     #[derive(Default, Serialize, Deserialize, Debug, Clone)]
     pub struct DrandBeacon {
         pub round: u64,
         pub signature: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub previous_signature: Option<String>,
         pub randomness: String,
     }
 
@@ -103,11 +742,7 @@ pub mod structs {
 
         pub fn wrap(&self) -> serde_json::Value {
             let payload = serde_json::to_value(self).unwrap();
-            json!(
-            {
-                "beacon": payload,
-                "input":"0x00",
-            })
+            json!({ "beacon": payload })
         }
     }
 
@@ -122,6 +757,11 @@ pub mod structs {
             self
         }
 
+        pub fn with_previous_signature(mut self, previous_signature: String) -> DrandBeaconBuilder {
+            self.0.previous_signature = Some(previous_signature);
+            self
+        }
+
         pub fn with_randomness(mut self, randomness: String) -> DrandBeaconBuilder {
             self.0.randomness = randomness;
             self
@@ -132,152 +772,668 @@ pub mod structs {
         }
     }
 
+    /**
+     * How many verified beacons we keep in memory, the oldest rounds are dropped first
+     */
+    pub const BEACON_HISTORY_SIZE: usize = 128;
+
+    /**
+     * How many deltas the journal log takes before the whole state is written again
+     */
+    pub const JOURNAL_COMPACT_SIZE: u64 = 1024;
+
     pub struct InputBufferManager {
         pub messages: VecDeque<Item>,
-        pub flag_to_hold: Flag,
         pub request_count: Cell<usize>,
-        pub last_beacon: Cell<Option<Beacon>>,
+        /**
+         * Beacons by timestamp, since rounds restart when the drand chain rotates
+         */
+        pub beacons: BTreeMap<u64, Beacon>,
         pub pending_beacon_timestamp: Cell<u64>,
-        pub randomness_salt: Cell<u64>,
-        pub is_inspecting: bool,
+        pub advance_cursor: DrawCursor,
+        pub inspect_cursor: DrawCursor,
+        pub state: LifecycleState,
+        /**
+         * Id of the last input fetched from the rollup server, the one waiting our finish status
+         */
+        pub last_fetch_id: u64,
+        /**
+         * Id of the input the DApp is processing
+         */
+        pub dispatched_fetch_id: u64,
+        /**
+         * Draws of the advance input the DApp is processing
+         */
+        pub provenance: Vec<Provenance>,
+        pub journal_log: JournalLog,
+    }
+
+    /**
+     * Changes of the InputBufferManager not yet appended to the journal
+     */
+    #[derive(Default)]
+    pub struct JournalLog {
+        pub changes: Vec<BufferChange>,
+        pub counters: Option<JournalCounters>,
+        /**
+         * Sequence of the last delta, and of the last one the snapshot holds
+         */
+        pub sequence: u64,
+        pub snapshot_sequence: u64,
+    }
+
+    /**
+     * Change to one of the buffers, replayed in order on restart
+     */
+    #[derive(Serialize, Deserialize)]
+    pub enum BufferChange {
+        Input(Item),
+        Consumed,
+        Beacon(Beacon),
+        Provenance(Provenance),
+        ProvenanceTaken,
+    }
+
+    /**
+     * Values of the InputBufferManager small enough to be written whole on every change
+     */
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct JournalCounters {
+        pub pending_beacon_timestamp: u64,
+        pub advance_cursor: DrawCursor,
+        pub state: LifecycleState,
+        pub last_fetch_id: u64,
+        pub dispatched_fetch_id: u64,
+    }
+
+    /**
+     * One line of the journal log
+     */
+    #[derive(Serialize, Deserialize)]
+    pub struct JournalDelta {
+        pub sequence: u64,
+        pub counters: JournalCounters,
+        pub changes: Vec<BufferChange>,
+    }
+
+    /**
+     * State of the InputBufferManager that must survive a restart of the middleware
+     */
+    #[derive(Default, Serialize, Deserialize)]
+    pub struct Journal {
+        /**
+         * Sequence of the last delta of the log this snapshot holds
+         */
+        #[serde(default)]
+        pub sequence: u64,
+        pub messages: Vec<Item>,
+        pub beacons: Vec<Beacon>,
+        pub pending_beacon_timestamp: u64,
+        pub advance_cursor: DrawCursor,
+        #[serde(default)]
+        pub state: LifecycleState,
+        #[serde(default)]
+        pub last_fetch_id: u64,
+        #[serde(default)]
+        pub dispatched_fetch_id: u64,
+        #[serde(default)]
+        pub provenance: Vec<Provenance>,
     }
 
     pub struct AppState {
         pub input_buffer_manager: Arc<Mutex<InputBufferManager>>,
-        pub drand_period: u64,
-        pub drand_genesis_time: u64,
-        pub safe_seconds: u64,
+        pub journal_path: Option<PathBuf>,
+        pub drand: RwLock<DrandConfig>,
+        /**
+         * Where the drand config updates are saved, kept only in memory when missing
+         */
+        pub drand_config_path: Option<PathBuf>,
+        /**
+         * Only advance inputs sent by this address may update the drand config
+         */
+        pub config_owner: Option<Address>,
+        pub rollup_url: String,
+        pub provenance_mode: ProvenanceMode,
+        pub derivation: DerivationScheme,
         pub version: String,
+        pub metrics: Metrics,
     }
 
     impl AppState {
-        pub fn new() -> AppState {
-            let manager = InputBufferManager::default();
-            let drand_period = var("DRAND_PERIOD")
-                .expect("Missing env DRAND_PERIOD")
-                .parse::<u64>()
-                .unwrap();
-            let drand_genesis_time = var("DRAND_GENESIS_TIME")
-                .expect("Missing env DRAND_GENESIS_TIME")
-                .parse::<u64>()
-                .unwrap();
-            let safe_seconds = var("DRAND_SAFE_SECONDS")
-                .expect("Missing env DRAND_SAFE_SECONDS")
-                .parse::<u64>()
-                .unwrap();
+        pub fn new(config: &Config) -> Result<AppState, ConfigError> {
+            let mut app_state = AppState::with_config(config, Some(config.journal_path.clone()))?;
+            app_state.drand_config_path = Some(config.config_path.clone());
+            Ok(app_state)
+        }
+        /**
+         * State configured from the env only
+         */
+        #[cfg(test)]
+        pub fn with_journal(journal_path: Option<PathBuf>) -> AppState {
+            let config = Config::from_env().expect("Invalid config");
+            AppState::with_config(&config, journal_path).expect("Invalid journal")
+        }
+        /**
+         * Restore the state from the journal snapshot and its log, if any, and keep it updated.
+         * Without a journal the state lives only in memory, and so do the drand config updates.
+         * A journal that can't be read is an error, so the held inputs in it are never overwritten.
+         */
+        fn with_config(
+            config: &Config,
+            journal_path: Option<PathBuf>,
+        ) -> Result<AppState, ConfigError> {
+            let mut manager = match journal_path.as_deref().map(restore_journal) {
+                Some(Ok(manager)) => {
+                    info!(
+                        "Restored {} inputs and {} beacons from journal",
+                        manager.messages.len(),
+                        manager.beacons.len()
+                    );
+                    manager
+                }
+                Some(Err(e)) => {
+                    return Err(ConfigError::Journal {
+                        path: journal_path.unwrap_or_default().display().to_string(),
+                        cause: e.to_string(),
+                    })
+                }
+                None => InputBufferManager::default(),
+            };
+            // the restored deltas are folded into a new snapshot
+            if let Some(path) = &journal_path {
+                compact_journal(path, &mut manager);
+            }
             let version: Option<&str> = option_env!("CARGO_PKG_VERSION");
-            AppState {
+            Ok(AppState {
                 input_buffer_manager: Arc::new(Mutex::new(manager)),
-                drand_period,
-                drand_genesis_time,
-                safe_seconds,
+                journal_path,
+                drand: RwLock::new(config.drand.clone()),
+                drand_config_path: None,
+                config_owner: config.config_owner,
+                rollup_url: config.rollup_url.clone(),
+                provenance_mode: config.provenance_mode,
+                derivation: config.derivation,
                 version: version.unwrap_or("unknown").to_string(),
-            }
+                metrics: Metrics::default(),
+            })
         }
-        pub fn get_randomness_for_timestamp(&self, query_timestamp: u64) -> Option<String> {
-            let mut manager = match self.input_buffer_manager.try_lock() {
-                Ok(manager) => manager,
-                Err(_) => return None,
+        pub async fn get_randomness(&self, query: &RandomQuery) -> Option<[u8; 32]> {
+            let mut manager = self.input_buffer_manager.lock().await;
+
+            let randomness = match self.find_beacon(&manager, query) {
+                Ok(beacon) => manager.draw(
+                    &beacon,
+                    &query.domain,
+                    self.derivation,
+                    self.provenance_mode,
+                ),
+                Err(pending_timestamp) => {
+                    manager.set_pending_beacon_timestamp(pending_timestamp);
+                    None
+                }
             };
-            let safe_query_timestamp = query_timestamp + self.safe_seconds;
-            match manager.last_beacon.take() {
-                Some(beacon) => {
-                    info!(
-                        "beacon time {} vs {} request time",
-                        beacon.timestamp, query_timestamp
-                    );
-                    // Check the beacon timestamp against the safe query timestamp
-                    if safe_query_timestamp < beacon.timestamp {
-                        let salt = manager.randomness_salt.take() + 1;
-                        manager.randomness_salt.set(salt);
-
-                        let mut hasher = Sha3_256::new();
-                        hasher.update([beacon.randomness.as_bytes(), &salt.to_le_bytes()].concat());
-                        let randomness = hasher.finalize();
-                        manager.flag_to_hold.release();
-                        manager.last_beacon.set(Some(beacon));
-                        Some(hex::encode(randomness))
-                    } else {
-                        manager.set_pending_beacon_timestamp(safe_query_timestamp);
-                        manager.last_beacon.set(Some(beacon));
-                        None
+            if randomness.is_some() {
+                self.metrics.randomness_served.inc();
+            }
+            self.persist(&mut manager);
+            randomness
+        }
+        #[cfg(test)]
+        pub async fn get_randomness_for_timestamp(
+            &self,
+            query_timestamp: u64,
+            domain: &str,
+        ) -> Option<[u8; 32]> {
+            self.get_randomness(&RandomQuery {
+                timestamp: Some(query_timestamp),
+                domain: domain.to_string(),
+                ..Default::default()
+            })
+            .await
+        }
+        #[cfg(test)]
+        pub async fn get_randomness_for_round(
+            &self,
+            query_round: u64,
+            domain: &str,
+        ) -> Option<[u8; 32]> {
+            self.get_randomness(&RandomQuery {
+                round: Some(query_round),
+                domain: domain.to_string(),
+                ..Default::default()
+            })
+            .await
+        }
+        /**
+         * Draw every spec of a batch, only when the beacons of all of them are known.
+         * Otherwise nothing is drawn and the oldest missing beacon becomes the pending one,
+         * so a newer beacon never arrives first and skips it.
+         */
+        pub async fn get_batch_randomness(
+            &self,
+            specs: &[RandomSpec],
+        ) -> Option<Vec<Vec<[u8; 32]>>> {
+            let mut manager = self.input_buffer_manager.lock().await;
+
+            let mut beacons = Vec::with_capacity(specs.len());
+            let mut pending_timestamp = None;
+            for spec in specs {
+                match self.find_beacon(&manager, &spec.query) {
+                    Ok(beacon) => beacons.push(beacon),
+                    Err(timestamp) => {
+                        pending_timestamp = Some(
+                            pending_timestamp
+                                .map_or(timestamp, |pending| u64::min(pending, timestamp)),
+                        )
                     }
                 }
-                None => {
-                    manager.set_pending_beacon_timestamp(safe_query_timestamp);
-                    None
-                }
             }
+            if let Some(timestamp) = pending_timestamp {
+                manager.set_pending_beacon_timestamp(timestamp);
+                self.persist(&mut manager);
+                return None;
+            }
+
+            let batch: Option<Vec<Vec<_>>> = specs
+                .iter()
+                .zip(beacons)
+                .map(|(spec, beacon)| {
+                    (0..spec.count)
+                        .map(|_| {
+                            manager.draw(
+                                &beacon,
+                                &spec.query.domain,
+                                self.derivation,
+                                self.provenance_mode,
+                            )
+                        })
+                        .collect()
+                })
+                .collect();
+            if let Some(batch) = &batch {
+                let served = batch.iter().map(Vec::len).sum::<usize>();
+                self.metrics.randomness_served.add(served as u64);
+            }
+            self.persist(&mut manager);
+            batch
         }
-        pub fn keep_newest_beacon(&self, drand_beacon: DrandBeacon) {
-            let beacon_time = (drand_beacon.round * self.drand_period) + self.drand_genesis_time;
-            info!(
-                "Calculated beacon time {} for round {}",
-                beacon_time, drand_beacon.round
-            );
-            let manager = self.input_buffer_manager.try_lock().unwrap();
-            if let Some(current_beacon) = manager.last_beacon.take() {
-                if current_beacon.round < drand_beacon.round {
-                    info!("Set new beacon");
+        /**
+         * Beacon of exactly this round, setting it as the pending beacon when it did not arrive yet
+         */
+        pub async fn get_beacon_of_round(&self, round: u64) -> Option<Beacon> {
+            let mut manager = self.input_buffer_manager.lock().await;
 
-                    let beacon = Beacon::builder()
-                        .with_drand_beacon(&drand_beacon)
-                        .with_timestamp(beacon_time)
-                        .build();
+            let timestamp = self.timestamp_for_round(round)?;
+            if let Some(beacon) = manager.beacon_of_round(round, timestamp) {
+                return Some(beacon.clone());
+            }
+            // the first round after the previous round time is the requested round
+            let previous_round = round.saturating_sub(1);
+            let previous_time = self.timestamp_for_round(previous_round).unwrap_or_default();
+            manager.set_pending_beacon_timestamp(previous_time);
+            self.persist(&mut manager);
+            None
+        }
+        /**
+         * The beacon of this round is missing and older than every beacon of a full history,
+         * so it would be ignored when it arrives and waiting for it would never end.
+         * A missing round only skipped by a newer beacon is still kept when it arrives.
+         */
+        pub async fn is_round_too_old(&self, round: u64) -> bool {
+            let Some(timestamp) = self.timestamp_for_round(round) else {
+                return false;
+            };
+            let manager = self.input_buffer_manager.lock().await;
+            !manager.beacons.contains_key(&timestamp) && manager.is_older_than_history(timestamp)
+        }
+        /**
+         * Round whose beacon answers the query, the first round after the safe query timestamp
+         * in the timestamp mode, so the draw never depends on which beacons were sent
+         */
+        pub fn round_for_query(&self, query: &RandomQuery) -> Result<RoundTimestamp, CheckerError> {
+            match (query.round, query.timestamp) {
+                (Some(query_round), _) => self.round_timestamp(query_round),
+                (None, Some(query_timestamp)) => self.safe_round_for_timestamp(query_timestamp),
+                (None, None) => Err(CheckerError::InvalidRandomQuery {
+                    cause: "expected either timestamp or round".to_string(),
+                }),
+            }
+        }
+        /**
+         * Round drawn for the timestamp, the first one after the timestamp plus the safe seconds
+         */
+        pub fn safe_round_for_timestamp(
+            &self,
+            timestamp: u64,
+        ) -> Result<RoundTimestamp, CheckerError> {
+            let safe_timestamp = timestamp.checked_add(self.safe_seconds()).ok_or_else(|| {
+                CheckerError::InvalidRandomQuery {
+                    cause: format!("timestamp {} is too far in the future", timestamp),
+                }
+            })?;
+            self.round_timestamp(self.round_for_timestamp(safe_timestamp))
+        }
+        /**
+         * Round with its beacon time, an error when that time does not fit in a u64
+         */
+        pub fn round_timestamp(&self, round: u64) -> Result<RoundTimestamp, CheckerError> {
+            match self.timestamp_for_round(round) {
+                Some(timestamp) => Ok(RoundTimestamp { round, timestamp }),
+                None => Err(CheckerError::InvalidRandomQuery {
+                    cause: format!("round {} is too far in the future", round),
+                }),
+            }
+        }
+        /**
+         * Beacon of the exact round of the query, or the timestamp of the beacon we need to wait for
+         */
+        fn find_beacon(
+            &self,
+            manager: &InputBufferManager,
+            query: &RandomQuery,
+        ) -> Result<Beacon, u64> {
+            let Ok(RoundTimestamp {
+                round: query_round,
+                timestamp: query_time,
+            }) = self.round_for_query(query)
+            else {
+                return Err(0);
+            };
+            let beacon = manager
+                .beacon_of_round(query_round, query_time)
+                .map(|beacon| {
+                    info!(
+                        "beacon round {} time {} vs {:?} request time",
+                        beacon.round, beacon.timestamp, query.timestamp
+                    );
+                    beacon.clone()
+                });
 
-                    manager.last_beacon.set(Some(beacon));
-                } else {
-                    info!("Keep current beacon");
-                    manager.last_beacon.set(Some(current_beacon));
+            let pending_timestamp = match query.timestamp {
+                Some(query_timestamp) if query.round.is_none() => {
+                    query_timestamp.saturating_add(self.safe_seconds())
                 }
-            } else {
-                info!("No beacon, initializing");
+                // the first round after the previous round time is the requested round
+                _ => self
+                    .timestamp_for_round(query_round.saturating_sub(1))
+                    .unwrap_or_default(),
+            };
+            beacon.ok_or(pending_timestamp)
+        }
+        pub fn drand_config(&self) -> DrandConfig {
+            self.drand.read().unwrap().clone()
+        }
+        /**
+         * Apply a new drand config right away, no restart needed
+         */
+        pub fn set_drand_config(&self, drand: DrandConfig) {
+            *self.drand.write().unwrap() = drand;
+        }
+        pub fn safe_seconds(&self) -> u64 {
+            self.drand.read().unwrap().safe_seconds
+        }
+        /**
+         * First round whose beacon time is after the timestamp, on the chain trusted at that time
+         */
+        pub fn round_for_timestamp(&self, timestamp: u64) -> u64 {
+            self.drand.read().unwrap().round_for_timestamp(timestamp)
+        }
+        pub fn timestamp_for_round(&self, round: u64) -> Option<u64> {
+            self.drand.read().unwrap().timestamp_for_round(round)
+        }
+        pub async fn pending_beacon(&self) -> PendingBeacon {
+            let drand = self.drand_config();
+            let manager = self.input_buffer_manager.lock().await;
+            let timestamp = manager.awaited_beacon_timestamp();
+            let (chain, round) = match timestamp {
+                0 => (drand.current_chain(), None),
+                _ => (
+                    drand.chain_at(timestamp),
+                    Some(drand.round_for_timestamp(timestamp)),
+                ),
+            };
+            PendingBeacon {
+                timestamp,
+                round,
+                chain_hash: chain.chain_hash.clone(),
+                public_key: chain.public_key.clone(),
+                last_round: manager.last_beacon().map(|beacon| beacon.round),
+            }
+        }
+        pub async fn status(&self) -> MiddlewareStatus {
+            let drand = self.drand_config().to_env();
+            let manager = self.input_buffer_manager.lock().await;
+            MiddlewareStatus {
+                version: self.version.clone(),
+                drand,
+                config_owner: self.config_owner,
+                last_beacon: manager.last_beacon().map(|beacon| RoundTimestamp {
+                    round: beacon.round,
+                    timestamp: beacon.timestamp,
+                }),
+                pending_beacon_timestamp: manager.awaited_beacon_timestamp(),
+                buffered_inputs: manager.messages.len(),
+                state: manager.state,
+                inspecting: manager.is_inspecting(),
+            }
+        }
+        pub async fn render_metrics(&self) -> String {
+            let manager = self.input_buffer_manager.lock().await;
+            self.metrics.render(&Gauges {
+                buffered_inputs: manager.messages.len(),
+                pending_beacon_timestamp: manager.awaited_beacon_timestamp(),
+                last_beacon_round: manager.last_beacon().map(|beacon| beacon.round),
+            })
+        }
+        pub async fn last_beacon_timestamp(&self) -> Option<u64> {
+            let manager = self.input_buffer_manager.lock().await;
+            manager.last_beacon().map(|beacon| beacon.timestamp)
+        }
+        #[cfg(test)]
+        pub async fn keep_beacon(&self, drand_beacon: DrandBeacon) {
+            let beacon_time = self.timestamp_for_round(drand_beacon.round).unwrap();
+            self.keep_beacon_at(drand_beacon, beacon_time).await;
+        }
+        /**
+         * Keep the beacon with its time on the chain that verified it
+         */
+        #[cfg(test)]
+        pub async fn keep_beacon_at(&self, drand_beacon: DrandBeacon, beacon_time: u64) {
+            self.keep_beacons(vec![(drand_beacon, beacon_time)]).await;
+        }
+        /**
+         * Keep the beacons with their time on the chain that verified them,
+         * then settle the held inputs with the newest one in a single step.
+         * Returns the rounds kept and the duplicate or stale rounds ignored.
+         */
+        pub async fn keep_beacons(
+            &self,
+            drand_beacons: Vec<(DrandBeacon, u64)>,
+        ) -> (Vec<u64>, Vec<u64>) {
+            let mut manager = self.input_buffer_manager.lock().await;
+            let was_holding = manager.state.is_holding();
 
+            let mut newest_time = None;
+            let (mut kept, mut ignored) = (vec![], vec![]);
+            for (drand_beacon, beacon_time) in drand_beacons {
+                info!(
+                    "Calculated beacon time {} for round {}",
+                    beacon_time, drand_beacon.round
+                );
                 let beacon = Beacon::builder()
                     .with_drand_beacon(&drand_beacon)
                     .with_timestamp(beacon_time)
                     .build();
+                if manager.add_beacon(beacon) {
+                    kept.push(drand_beacon.round);
+                    newest_time = newest_time.max(Some(beacon_time));
+                } else {
+                    self.metrics.beacons_ignored.inc();
+                    ignored.push(drand_beacon.round);
+                }
+            }
 
-                manager.last_beacon.set(Some(beacon));
+            if let Some(timestamp) = newest_time {
+                manager.apply(LifecycleEvent::Beacon { timestamp });
             }
+            if was_holding && !manager.state.is_holding() {
+                self.metrics.hold_released(manager.last_fetch_id);
+                manager.pending_beacon_timestamp.set(0);
+            }
+            self.persist(&mut manager);
+            (kept, ignored)
         }
         pub async fn store_input(&self, rollup_input: &RollupInput) {
             let mut manager = self.input_buffer_manager.lock().await;
-            let request = serde_json::to_string(rollup_input).unwrap();
-            manager.messages.push_back(Item { request });
+            let request = rollup_input.raw_body();
+            let fetch_id = manager.last_fetch_id;
+            manager.store_input(Item { request, fetch_id });
+            self.metrics.buffered(manager.messages.len());
+            self.persist(&mut manager);
         }
         pub async fn consume_input(&self) -> Option<Item> {
             let mut manager = self.input_buffer_manager.lock().await;
-            manager.consume_input()
+            let item = manager.consume_input();
+            self.persist(&mut manager);
+            item
         }
-        pub async fn set_inspecting(&self, value: bool) {
+        /**
+         * Reset the draw sequence when an input is dispatched to the DApp
+         */
+        pub async fn start_input(&self, rollup_input: &RollupInput) {
             let mut manager = self.input_buffer_manager.lock().await;
-            manager.is_inspecting = value;
+            manager.start_input(rollup_input);
+            self.persist(&mut manager);
         }
-        pub fn is_inspecting(&self) -> bool {
-            #[cfg(target_arch = "riscv64")]
-            {
-                let manager = match self.input_buffer_manager.try_lock() {
-                    Ok(manager) => manager,
-                    Err(_) => return false,
+        /**
+         * Count a new input fetched from the rollup server and return its id
+         */
+        pub async fn fetched(&self) -> u64 {
+            let mut manager = self.input_buffer_manager.lock().await;
+            manager.last_fetch_id += 1;
+            self.persist(&mut manager);
+            manager.last_fetch_id
+        }
+        /**
+         * The DApp received the input fetched with this id
+         */
+        pub async fn dispatched(&self, fetch_id: u64) {
+            let mut manager = self.input_buffer_manager.lock().await;
+            manager.dispatched_fetch_id = fetch_id;
+            self.persist(&mut manager);
+        }
+        /**
+         * Status to send with the next finish, given the DApp status of its current input.
+         * The rollup server waits the status of the last input we fetched,
+         * the older ones were accepted when we fetched the newer ones.
+         */
+        pub async fn status_for_rollup(&self, request: &RequestRollups) -> RollupStatus {
+            let manager = self.input_buffer_manager.lock().await;
+            if manager.dispatched_fetch_id == manager.last_fetch_id {
+                let status = if request.is_reject() {
+                    "reject"
+                } else {
+                    "accept"
+                };
+                return RollupStatus {
+                    status,
+                    held_reject: None,
                 };
-                return manager.is_inspecting;
             }
-            false
-        }
-    }
 
-    impl Flag {
-        fn new() -> Flag {
-            Flag { is_holding: false }
+            // inspects never change the rollup state, so their rejects do not matter
+            let held_reject = (request.is_reject() && !manager.is_inspecting())
+                .then_some(manager.advance_cursor.input_index);
+            RollupStatus {
+                status: "accept",
+                held_reject,
+            }
+        }
+        /**
+         * Take the provenance of the draws made since the last call
+         */
+        pub async fn take_provenance(&self) -> Vec<Provenance> {
+            let mut manager = self.input_buffer_manager.lock().await;
+            let provenance = manager.take_provenance();
+            self.persist(&mut manager);
+            provenance
+        }
+        /**
+         * Input the DApp is processing, so we can tie its outputs to it
+         */
+        pub async fn output_owner(&self) -> OutputOwner {
+            let manager = self.input_buffer_manager.lock().await;
+            OutputOwner {
+                input_index: (!manager.is_inspecting())
+                    .then_some(manager.advance_cursor.input_index),
+                buffered: manager.dispatched_fetch_id != manager.last_fetch_id,
+            }
+        }
+        /**
+         * Append the changes to the journal log, a failure is logged but never stops the DApp.
+         * The whole state is written again when the log gets long or cannot be appended to.
+         */
+        fn persist(&self, manager: &mut InputBufferManager) {
+            let Some(delta) = manager.take_journal_delta() else {
+                return;
+            };
+            let Some(path) = &self.journal_path else {
+                return;
+            };
+            if delta.sequence < manager.journal_log.snapshot_sequence + JOURNAL_COMPACT_SIZE {
+                match append_journal(path, &delta) {
+                    Ok(()) => return,
+                    Err(e) => error!("Error appending to journal {}: {}", path.display(), e),
+                }
+            }
+            compact_journal(path, manager);
+        }
+        /**
+         * Hold the next inputs until the pending beacon arrives, false when the DApp is inspecting
+         */
+        pub async fn hold(&self) -> bool {
+            let mut manager = self.input_buffer_manager.lock().await;
+            let timestamp = manager.pending_beacon_timestamp.get();
+            let was_holding = manager.state.is_holding();
+            let held = manager.apply(LifecycleEvent::Hold { timestamp });
+            if held && !was_holding {
+                self.metrics.hold_started(manager.last_fetch_id);
+            }
+            self.persist(&mut manager);
+            held
         }
+        pub async fn state(&self) -> LifecycleState {
+            self.input_buffer_manager.lock().await.state
+        }
+        pub async fn is_holding(&self) -> bool {
+            self.state().await.is_holding()
+        }
+    }
 
-        pub fn hold_up(&mut self) {
-            self.is_holding = true;
+    /**
+     * Read the journal snapshot, then replay the deltas of its log it does not hold yet
+     */
+    fn restore_journal(path: &Path) -> Result<InputBufferManager, Box<dyn Error>> {
+        let journal = read_journal(path)?.unwrap_or_default();
+        let snapshot_sequence = journal.sequence;
+        let mut manager = InputBufferManager::from(journal);
+        for delta in read_journal_log(path)? {
+            if delta.sequence > snapshot_sequence {
+                manager.replay(delta);
+            }
         }
+        manager.journal_log.changes.clear();
+        manager.journal_log.counters = Some(manager.counters());
+        Ok(manager)
+    }
 
-        pub fn release(&mut self) {
-            self.is_holding = false;
+    /**
+     * Write the whole state as the new snapshot, which empties the journal log
+     */
+    fn compact_journal(path: &Path, manager: &mut InputBufferManager) {
+        match write_journal(path, &manager.journal()) {
+            Ok(()) => manager.journal_log.snapshot_sequence = manager.journal_log.sequence,
+            Err(e) => error!("Error writing journal {}: {}", path.display(), e),
         }
     }
 
@@ -285,17 +1441,135 @@ pub mod structs {
         fn default() -> Self {
             InputBufferManager {
                 messages: VecDeque::new(),
-                flag_to_hold: Flag::new(),
                 request_count: Cell::new(0),
-                last_beacon: Cell::new(None),
+                beacons: BTreeMap::new(),
                 pending_beacon_timestamp: Cell::new(0),
-                randomness_salt: Cell::new(0),
-                is_inspecting: false,
+                advance_cursor: DrawCursor::default(),
+                inspect_cursor: DrawCursor::default(),
+                state: LifecycleState::Idle,
+                last_fetch_id: 0,
+                dispatched_fetch_id: 0,
+                provenance: Vec::new(),
+                journal_log: JournalLog::default(),
             }
         }
     }
 
+    impl From<Journal> for InputBufferManager {
+        fn from(journal: Journal) -> Self {
+            let mut manager = InputBufferManager {
+                request_count: Cell::new(journal.messages.len()),
+                messages: journal.messages.into(),
+                pending_beacon_timestamp: Cell::new(journal.pending_beacon_timestamp),
+                advance_cursor: journal.advance_cursor,
+                state: journal.state,
+                last_fetch_id: journal.last_fetch_id,
+                dispatched_fetch_id: journal.dispatched_fetch_id,
+                provenance: journal.provenance,
+                journal_log: JournalLog {
+                    sequence: journal.sequence,
+                    snapshot_sequence: journal.sequence,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            for beacon in journal.beacons {
+                manager.add_beacon(beacon);
+            }
+            manager
+        }
+    }
+
     impl InputBufferManager {
+        pub fn journal(&self) -> Journal {
+            Journal {
+                sequence: self.journal_log.sequence,
+                messages: self.messages.iter().cloned().collect(),
+                beacons: self.beacons.values().cloned().collect(),
+                pending_beacon_timestamp: self.pending_beacon_timestamp.get(),
+                advance_cursor: self.advance_cursor,
+                state: self.state,
+                last_fetch_id: self.last_fetch_id,
+                dispatched_fetch_id: self.dispatched_fetch_id,
+                provenance: self.provenance.clone(),
+            }
+        }
+
+        pub fn counters(&self) -> JournalCounters {
+            JournalCounters {
+                pending_beacon_timestamp: self.pending_beacon_timestamp.get(),
+                advance_cursor: self.advance_cursor,
+                state: self.state,
+                last_fetch_id: self.last_fetch_id,
+                dispatched_fetch_id: self.dispatched_fetch_id,
+            }
+        }
+
+        /**
+         * Changes since the last delta, None when nothing changed
+         */
+        pub fn take_journal_delta(&mut self) -> Option<JournalDelta> {
+            let counters = self.counters();
+            let log = &mut self.journal_log;
+            if log.changes.is_empty() && log.counters == Some(counters) {
+                return None;
+            }
+            log.sequence += 1;
+            log.counters = Some(counters);
+            Some(JournalDelta {
+                sequence: log.sequence,
+                counters,
+                changes: std::mem::take(&mut log.changes),
+            })
+        }
+
+        /**
+         * Apply a delta read from the journal log
+         */
+        pub fn replay(&mut self, delta: JournalDelta) {
+            for change in delta.changes {
+                match change {
+                    BufferChange::Input(item) => self.messages.push_back(item),
+                    BufferChange::Consumed => {
+                        self.messages.pop_front();
+                    }
+                    BufferChange::Beacon(beacon) => {
+                        self.add_beacon(beacon);
+                    }
+                    BufferChange::Provenance(provenance) => self.provenance.push(provenance),
+                    BufferChange::ProvenanceTaken => self.provenance.clear(),
+                }
+            }
+            self.request_count.set(self.messages.len());
+
+            let counters = delta.counters;
+            self.pending_beacon_timestamp
+                .set(counters.pending_beacon_timestamp);
+            self.advance_cursor = counters.advance_cursor;
+            self.state = counters.state;
+            self.last_fetch_id = counters.last_fetch_id;
+            self.dispatched_fetch_id = counters.dispatched_fetch_id;
+            self.journal_log.sequence = delta.sequence;
+        }
+
+        pub fn store_input(&mut self, item: Item) {
+            self.journal_log
+                .changes
+                .push(BufferChange::Input(item.clone()));
+            self.messages.push_back(item);
+            self.request_count.set(self.messages.len());
+        }
+
+        /**
+         * Take the draws recorded since the last call
+         */
+        pub fn take_provenance(&mut self) -> Vec<Provenance> {
+            if !self.provenance.is_empty() {
+                self.journal_log.changes.push(BufferChange::ProvenanceTaken);
+            }
+            std::mem::take(&mut self.provenance)
+        }
+
         pub fn set_pending_beacon_timestamp(&mut self, timestamp: u64) {
             let current = self.pending_beacon_timestamp.take();
             // mantendo o mais recente para economizar transacoes
@@ -308,16 +1582,207 @@ pub mod structs {
             }
         }
 
+        /**
+         * Time of the beacon the held inputs wait, 0 when nothing is held
+         */
+        pub fn awaited_beacon_timestamp(&self) -> u64 {
+            match self.state {
+                LifecycleState::AwaitingBeacon { timestamp } => timestamp,
+                _ => 0,
+            }
+        }
+
+        /**
+         * Add the beacon to the history, false when it was already known
+         * or is older than every beacon of a full history
+         */
+        pub fn add_beacon(&mut self, beacon: Beacon) -> bool {
+            if self.beacons.contains_key(&beacon.timestamp) {
+                info!("Keep current beacon for round {}", beacon.round);
+                return false;
+            }
+            if self.is_older_than_history(beacon.timestamp) {
+                info!("Ignore stale beacon for round {}", beacon.round);
+                return false;
+            }
+
+            info!("Add beacon for round {}", beacon.round);
+            self.journal_log
+                .changes
+                .push(BufferChange::Beacon(beacon.clone()));
+            self.beacons.insert(beacon.timestamp, beacon);
+
+            while self.beacons.len() > BEACON_HISTORY_SIZE {
+                self.beacons.pop_first();
+            }
+            true
+        }
+
+        /**
+         * A full history never keeps a beacon older than all of its beacons
+         */
+        pub fn is_older_than_history(&self, timestamp: u64) -> bool {
+            let oldest = self
+                .beacons
+                .first_key_value()
+                .map(|(timestamp, _)| *timestamp);
+            self.beacons.len() >= BEACON_HISTORY_SIZE
+                && oldest.is_some_and(|oldest| timestamp < oldest)
+        }
+
+        pub fn last_beacon(&self) -> Option<&Beacon> {
+            self.beacons.values().next_back()
+        }
+
+        /**
+         * Beacon of the round at its time, None when that round did not arrive
+         */
+        pub fn beacon_of_round(&self, round: u64, timestamp: u64) -> Option<&Beacon> {
+            self.beacons
+                .get(&timestamp)
+                .filter(|beacon| beacon.round == round)
+        }
+
+        /**
+         * Derive the next draw of the current input from the beacon randomness
+         */
+        pub fn draw(
+            &mut self,
+            beacon: &Beacon,
+            domain: &str,
+            derivation: DerivationScheme,
+            provenance_mode: ProvenanceMode,
+        ) -> Option<[u8; 32]> {
+            let cursor = self.current_cursor();
+            let draw = Draw {
+                round: beacon.round,
+                input_index: cursor.input_index,
+                sequence: cursor.sequence,
+                domain: domain.to_owned(),
+            };
+
+            match derive_seed(derivation, &beacon.randomness, &draw) {
+                Ok(seed) => {
+                    info!("Derived randomness for {:?}", draw);
+                    // inspects cannot emit notices, so only the advance draws are recorded
+                    if provenance_mode != ProvenanceMode::Off && !self.is_inspecting() {
+                        let provenance = Provenance {
+                            round: draw.round,
+                            signature: beacon.signature.clone(),
+                            timestamp: beacon.timestamp,
+                            input_index: draw.input_index,
+                            sequence: draw.sequence,
+                            domain: draw.domain,
+                            derivation,
+                            output: hex::encode(seed),
+                        };
+                        self.journal_log
+                            .changes
+                            .push(BufferChange::Provenance(provenance.clone()));
+                        self.provenance.push(provenance);
+                    }
+                    self.next_draw();
+                    Some(seed)
+                }
+                Err(e) => {
+                    error!(
+                        "Error deriving randomness for round {}: {}",
+                        beacon.round, e
+                    );
+                    None
+                }
+            }
+        }
+
+        pub fn start_input(&mut self, rollup_input: &RollupInput) {
+            match rollup_input.request_type {
+                RollupState::Advance => {
+                    let input_index = rollup_input
+                        .data
+                        .metadata
+                        .as_ref()
+                        .map(|metadata| metadata.input_index_u64())
+                        .unwrap_or_default();
+
+                    self.advance_cursor = DrawCursor {
+                        input_index,
+                        sequence: 0,
+                    };
+                    self.take_provenance();
+                }
+                RollupState::Inspect => {
+                    // inspects draw on their own sequence, so they never change the advance values
+                    self.inspect_cursor = DrawCursor {
+                        input_index: self.advance_cursor.input_index,
+                        sequence: 0,
+                    };
+                }
+                RollupState::Unknown => return,
+            }
+
+            // a new input waits no beacon yet
+            self.pending_beacon_timestamp.set(0);
+            let inspect = matches!(rollup_input.request_type, RollupState::Inspect);
+            let buffered = self.messages.len();
+            self.apply(LifecycleEvent::Dispatch { inspect, buffered });
+        }
+
+        /**
+         * Move to the next state, keeping the current one when the event is not allowed
+         */
+        pub fn apply(&mut self, event: LifecycleEvent) -> bool {
+            match self.state.on(event) {
+                Some(state) => {
+                    if state != self.state {
+                        info!("State {:?} -> {:?} on {:?}", self.state, state, event);
+                    }
+                    self.state = state;
+                    true
+                }
+                None => {
+                    error!("Event {:?} not allowed on state {:?}", event, self.state);
+                    false
+                }
+            }
+        }
+
+        pub fn is_inspecting(&self) -> bool {
+            self.state == LifecycleState::Inspecting
+        }
+
+        pub fn current_cursor(&self) -> DrawCursor {
+            if self.is_inspecting() {
+                self.inspect_cursor
+            } else {
+                self.advance_cursor
+            }
+        }
+
+        pub fn next_draw(&mut self) {
+            let cursor = if self.is_inspecting() {
+                &mut self.inspect_cursor
+            } else {
+                &mut self.advance_cursor
+            };
+            cursor.sequence += 1;
+        }
+
         pub fn consume_input(&mut self) -> Option<Item> {
             info!("Consuming input");
             let buffer = self.messages.borrow_mut();
 
-            if buffer.is_empty() || self.flag_to_hold.is_holding {
+            if buffer.is_empty() || self.state.is_holding() {
                 return None;
             }
 
             let data = buffer.pop_front();
-            self.request_count.set(self.request_count.get() - 1);
+            self.request_count.set(buffer.len());
+            if data.is_some() {
+                self.journal_log.changes.push(BufferChange::Consumed);
+            }
+            if let Some(item) = &data {
+                self.dispatched_fetch_id = item.fetch_id;
+            }
             data
         }
     }
@@ -325,20 +1790,40 @@ pub mod structs {
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::sync::{Arc, RwLock};
 
     use tokio::sync::Mutex;
 
-    use super::structs::{AppState, Beacon, DrandBeacon, InputBufferManager};
+    use super::structs::{
+        AppState, Beacon, DerivationScheme, DrandBeacon, DrandChain, DrandConfig, DrandEnv,
+        DrandScheme, InputBufferManager, LifecycleEvent, LifecycleState, ProvenanceMode,
+        RequestRollups, RollupStatus, BEACON_HISTORY_SIZE,
+    };
+    use crate::rollup::input::{RollupInput, RollupInputDataMetadataBuilder, RollupState};
 
     fn create_app_state() -> AppState {
         let version: Option<&str> = option_env!("CARGO_PKG_VERSION");
         AppState {
             input_buffer_manager: Arc::new(Mutex::new(InputBufferManager::default())),
-            drand_period: 3,
-            drand_genesis_time: 1677685200,
-            safe_seconds: 5,
+            journal_path: None,
+            drand: RwLock::new(DrandConfig {
+                chains: vec![DrandChain {
+                    public_key: "".to_string(),
+                    scheme: DrandScheme::default(),
+                    period: 3,
+                    genesis_time: 1677685200,
+                    chain_hash: None,
+                    effective_time: 0,
+                }],
+                safe_seconds: 5,
+            }),
+            drand_config_path: None,
+            config_owner: None,
+            rollup_url: "".to_string(),
+            provenance_mode: ProvenanceMode::Off,
+            derivation: DerivationScheme::Sha3_256,
             version: version.unwrap_or("unknown").to_string(),
+            metrics: Default::default(),
         }
     }
 
@@ -346,27 +1831,49 @@ mod test {
     async fn test_app_state_init_beacon() {
         let app = create_app_state();
         let beacon = DrandBeacon::builder().with_round(2).build();
-        app.keep_newest_beacon(beacon);
+        app.keep_beacon(beacon).await;
         let manager = app.input_buffer_manager.lock().await;
-        assert_eq!(2, manager.last_beacon.take().unwrap().round);
+        assert_eq!(2, manager.last_beacon().unwrap().round);
+    }
+
+    #[test]
+    fn test_add_beacon_ignores_duplicate_and_stale_rounds() {
+        let mut manager = InputBufferManager::default();
+        let beacon = |round: u64| Beacon {
+            timestamp: 1677685200 + round * 3,
+            round,
+            ..Default::default()
+        };
+        for round in 1..=BEACON_HISTORY_SIZE as u64 {
+            assert!(manager.add_beacon(beacon(round * 2)));
+        }
+        assert!(!manager.add_beacon(beacon(2)));
+
+        // a full history drops the oldest beacon for a newer one, but never keeps an older one
+        assert!(!manager.add_beacon(beacon(1)));
+        assert!(manager.add_beacon(beacon(3)));
+        assert_eq!(manager.beacons.len(), BEACON_HISTORY_SIZE);
+        assert_eq!(manager.beacons.first_key_value().unwrap().1.round, 3);
     }
 
     #[actix_web::test]
     async fn test_app_state_keep_current_beacon() {
         let app = create_app_state();
         {
-            let manager = app.input_buffer_manager.lock().await;
-            manager.last_beacon.set(Some(Beacon {
+            let mut manager = app.input_buffer_manager.lock().await;
+            manager.add_beacon(Beacon {
                 timestamp: 1677685206,
                 round: 2,
                 randomness: "".to_string(),
-            }))
+                ..Default::default()
+            });
         }
         {
             let beacon = DrandBeacon::builder().with_round(1).build();
-            app.keep_newest_beacon(beacon);
+            app.keep_beacon(beacon).await;
             let manager = app.input_buffer_manager.lock().await;
-            assert_eq!(2, manager.last_beacon.take().unwrap().round);
+            assert_eq!(2, manager.last_beacon().unwrap().round);
+            assert_eq!(2, manager.beacons.len());
         }
     }
 
@@ -374,18 +1881,412 @@ mod test {
     async fn test_app_state_keep_new_beacon() {
         let app = create_app_state();
         {
-            let manager = app.input_buffer_manager.lock().await;
-            manager.last_beacon.set(Some(Beacon {
+            let mut manager = app.input_buffer_manager.lock().await;
+            manager.add_beacon(Beacon {
                 timestamp: 1677685206,
                 round: 2,
                 randomness: "".to_string(),
-            }))
+                ..Default::default()
+            });
         }
         {
             let beacon = DrandBeacon::builder().with_round(3).build();
-            app.keep_newest_beacon(beacon);
+            app.keep_beacon(beacon).await;
             let manager = app.input_buffer_manager.lock().await;
-            assert_eq!(3, manager.last_beacon.take().unwrap().round);
+            assert_eq!(3, manager.last_beacon().unwrap().round);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_beacon_history_is_bounded() {
+        let app = create_app_state();
+        let total = BEACON_HISTORY_SIZE as u64 + 10;
+        for round in 1..=total {
+            let beacon = DrandBeacon::builder().with_round(round).build();
+            app.keep_beacon(beacon).await;
+        }
+        let manager = app.input_buffer_manager.lock().await;
+        assert_eq!(BEACON_HISTORY_SIZE, manager.beacons.len());
+        assert_eq!(total, manager.last_beacon().unwrap().round);
+        assert_eq!(11, manager.beacons.values().next().unwrap().round);
+    }
+
+    #[actix_web::test]
+    async fn test_drand_config_schedules_rotation() {
+        let app = create_app_state();
+        let genesis = app.timestamp_for_round(0).unwrap();
+        let rotation_time = genesis + 300;
+        let config = app.drand_config().updated(&DrandEnv {
+            DRAND_PUBLIC_KEY: "next".to_string(),
+            DRAND_PERIOD: Some(2),
+            DRAND_GENESIS_TIME: Some(rotation_time - 20),
+            DRAND_EFFECTIVE_TIME: Some(rotation_time),
+            ..Default::default()
+        });
+        assert_eq!(config.chains.len(), 2);
+        assert_eq!(config.safe_seconds, 5);
+        assert_eq!(config.current_chain().scheme, DrandScheme::default());
+
+        // the rounds before the cut-over stay on the first chain
+        assert_eq!(config.chain_at(rotation_time - 1).public_key, "");
+        assert_eq!(config.chain_at(rotation_time).public_key, "next");
+        assert_eq!(config.round_for_timestamp(rotation_time - 4), 99);
+        assert_eq!(config.round_for_timestamp(rotation_time), 11);
+        assert_eq!(config.chains_for_round(9).count(), 1);
+        assert_eq!(config.chains_for_round(50).count(), 2);
+        assert_eq!(config.chains_for_round(150).count(), 1);
+        assert_eq!(
+            config.timestamp_for_round(150).unwrap(),
+            rotation_time + 280
+        );
+        assert_eq!(config.timestamp_for_round(u64::MAX), None);
+        let chain = DrandChain {
+            period: 1,
+            genesis_time: 0,
+            ..config.chains[0].clone()
+        };
+        assert_eq!(chain.round_for_timestamp(u64::MAX), u64::MAX);
+
+        // scheduling again at the same time replaces the rotation, keeping its missing values
+        let config = config.updated(&DrandEnv {
+            DRAND_PUBLIC_KEY: "other".to_string(),
+            DRAND_EFFECTIVE_TIME: Some(rotation_time),
+            ..Default::default()
+        });
+        assert_eq!(config.chains.len(), 2);
+        assert_eq!(config.current_chain().public_key, "other");
+        assert_eq!(config.current_chain().period, 2);
+
+        // without an effective time the chain replaces the history
+        let config = config.updated(&DrandEnv {
+            DRAND_PUBLIC_KEY: "now".to_string(),
+            DRAND_SAFE_SECONDS: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(config.chains.len(), 1);
+        assert_eq!(config.safe_seconds, 1);
+
+        // the same round of both chains is kept by its time
+        app.set_drand_config(app.drand_config().updated(&DrandEnv {
+            DRAND_PUBLIC_KEY: "next".to_string(),
+            DRAND_PERIOD: Some(2),
+            DRAND_GENESIS_TIME: Some(rotation_time - 20),
+            DRAND_EFFECTIVE_TIME: Some(rotation_time),
+            ..Default::default()
+        }));
+        let beacon = DrandBeacon::builder().with_round(50).build();
+        app.keep_beacon_at(beacon.clone(), genesis + 150).await;
+        app.keep_beacon(beacon).await;
+        let manager = app.input_buffer_manager.lock().await;
+        assert_eq!(manager.beacons.len(), 2);
+        assert_eq!(manager.last_beacon().unwrap().timestamp, rotation_time + 80);
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_randomness_from_first_round_after_timestamp() {
+        let app = create_app_state();
+        let genesis = app.timestamp_for_round(0).unwrap();
+
+        // round 10 (genesis + 30) is the first round after genesis + 24 + 5 safe seconds
+        let keep_round = async |round: u64| {
+            let beacon = DrandBeacon::builder()
+                .with_round(round)
+                .with_randomness(format!("{:064x}", round))
+                .build();
+            app.keep_beacon(beacon).await;
+        };
+
+        keep_round(12).await;
+        keep_round(10).await;
+        keep_round(11).await;
+        let later_arrival = app.get_randomness_for_timestamp(genesis + 24, "").await;
+
+        let app = create_app_state();
+        let beacon = DrandBeacon::builder()
+            .with_round(10)
+            .with_randomness(format!("{:064x}", 10))
+            .build();
+        app.keep_beacon(beacon).await;
+        let in_order_arrival = app.get_randomness_for_timestamp(genesis + 24, "").await;
+
+        assert!(later_arrival.is_some());
+        assert_eq!(later_arrival, in_order_arrival);
+
+        // a newer beacon never answers for the round due at the timestamp
+        let app = create_app_state();
+        let beacon = DrandBeacon::builder()
+            .with_round(11)
+            .with_randomness(format!("{:064x}", 11))
+            .build();
+        app.keep_beacon(beacon).await;
+        assert!(app
+            .get_randomness_for_timestamp(genesis + 24, "")
+            .await
+            .is_none());
+
+        // nothing after the requested timestamp yet
+        assert!(app
+            .get_randomness_for_timestamp(genesis + 30, "")
+            .await
+            .is_none());
+        let manager = app.input_buffer_manager.lock().await;
+        assert_eq!(genesis + 35, manager.pending_beacon_timestamp.get());
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_randomness_waits_for_the_manager() {
+        let app = create_app_state();
+        app.keep_beacon(
+            DrandBeacon::builder()
+                .with_round(10)
+                .with_randomness(format!("{:064x}", 10))
+                .build(),
+        )
+        .await;
+
+        // another worker, like /status, holds the manager while the DApp asks for randomness
+        let busy = async {
+            let _manager = app.input_buffer_manager.lock().await;
+            tokio::task::yield_now().await;
+        };
+        let (_, randomness) = tokio::join!(busy, app.get_randomness_for_round(10, ""));
+        assert!(randomness.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_records_provenance_only_when_enabled() {
+        let mut app = create_app_state();
+        app.keep_beacon(
+            DrandBeacon::builder()
+                .with_round(10)
+                .with_randomness(format!("{:064x}", 10))
+                .build(),
+        )
+        .await;
+
+        assert!(app.get_randomness_for_round(10, "").await.is_some());
+        assert!(app.input_buffer_manager.lock().await.provenance.is_empty());
+
+        app.provenance_mode = ProvenanceMode::Input;
+        assert!(app.get_randomness_for_round(10, "").await.is_some());
+        assert_eq!(app.input_buffer_manager.lock().await.provenance.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_draws_do_not_depend_on_inspects() {
+        // the draws come from round 10, the first round after genesis + 24 + 5 safe seconds
+        let genesis = 1677685200 + 24;
+        let advance = |input_index: u64| {
+            let metadata = RollupInputDataMetadataBuilder::default()
+                .with_input_index(input_index.into())
+                .build();
+            RollupInput::builder()
+                .with_metadata(metadata)
+                .with_request_type(RollupState::Advance)
+                .build()
+        };
+        let inspect = RollupInput::builder()
+            .with_request_type(RollupState::Inspect)
+            .build();
+        let beacon = || {
+            DrandBeacon::builder()
+                .with_round(10)
+                .with_randomness(format!("{:064x}", 10))
+                .build()
+        };
+
+        let app = create_app_state();
+        app.keep_beacon(beacon()).await;
+        app.start_input(&advance(7)).await;
+        let first = app.get_randomness_for_timestamp(genesis, "").await;
+        let second = app.get_randomness_for_timestamp(genesis, "").await;
+
+        let other = create_app_state();
+        other.keep_beacon(beacon()).await;
+        other.start_input(&advance(7)).await;
+        let other_first = other.get_randomness_for_timestamp(genesis, "").await;
+        other.start_input(&inspect).await;
+        let inspected = other.get_randomness_for_timestamp(genesis, "").await;
+        other.start_input(&advance(7)).await;
+        other.get_randomness_for_timestamp(genesis, "").await;
+        let other_second = other.get_randomness_for_timestamp(genesis, "").await;
+
+        assert!(first.is_some());
+        assert_ne!(first, second);
+        assert_eq!(first, other_first);
+        assert_eq!(first, inspected);
+        assert_eq!(second, other_second);
+
+        // another input or domain gives another value
+        other.start_input(&advance(8)).await;
+        assert_ne!(first, other.get_randomness_for_timestamp(genesis, "").await);
+        other.start_input(&advance(7)).await;
+        assert_ne!(
+            first,
+            other.get_randomness_for_timestamp(genesis, "cards").await
+        );
+    }
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        use LifecycleEvent::*;
+        use LifecycleState::*;
+
+        let advance = Dispatch {
+            inspect: false,
+            buffered: 0,
+        };
+        let advance_with_buffer = Dispatch {
+            inspect: false,
+            buffered: 2,
+        };
+        let inspect = Dispatch {
+            inspect: true,
+            buffered: 0,
+        };
+        let inspect_with_buffer = Dispatch {
+            inspect: true,
+            buffered: 2,
+        };
+        let awaiting = AwaitingBeacon { timestamp: 100 };
+
+        // every state against every kind of event
+        let table = [
+            (Idle, advance, Some(Idle)),
+            (Idle, advance_with_buffer, Some(Draining)),
+            (Idle, inspect, Some(Inspecting)),
+            (Idle, inspect_with_buffer, Some(Inspecting)),
+            (Idle, Hold { timestamp: 100 }, Some(awaiting)),
+            (Idle, Beacon { timestamp: 200 }, Some(Idle)),
+            (awaiting, advance, None),
+            (awaiting, advance_with_buffer, None),
+            (awaiting, inspect, None),
+            (awaiting, inspect_with_buffer, None),
+            (awaiting, Hold { timestamp: 50 }, Some(awaiting)),
+            (
+                awaiting,
+                Hold { timestamp: 150 },
+                Some(AwaitingBeacon { timestamp: 150 }),
+            ),
+            (awaiting, Beacon { timestamp: 100 }, Some(awaiting)),
+            (awaiting, Beacon { timestamp: 103 }, Some(Draining)),
+            (Inspecting, advance, Some(Idle)),
+            (Inspecting, advance_with_buffer, Some(Draining)),
+            (Inspecting, inspect, Some(Inspecting)),
+            (Inspecting, inspect_with_buffer, Some(Inspecting)),
+            (Inspecting, Hold { timestamp: 100 }, None),
+            (Inspecting, Beacon { timestamp: 200 }, Some(Inspecting)),
+            (Draining, advance, Some(Idle)),
+            (Draining, advance_with_buffer, Some(Draining)),
+            (Draining, inspect, Some(Inspecting)),
+            (Draining, inspect_with_buffer, Some(Inspecting)),
+            (Draining, Hold { timestamp: 100 }, Some(awaiting)),
+            (Draining, Beacon { timestamp: 200 }, Some(Draining)),
+        ];
+
+        for (state, event, expected) in table {
+            assert_eq!(state.on(event), expected, "{:?} on {:?}", state, event);
         }
     }
+
+    #[test]
+    fn test_manager_keeps_state_on_rejected_event() {
+        let mut manager = InputBufferManager::default();
+        assert!(manager.apply(LifecycleEvent::Hold { timestamp: 100 }));
+        assert!(manager.state.is_holding());
+
+        assert!(!manager.apply(LifecycleEvent::Dispatch {
+            inspect: false,
+            buffered: 0,
+        }));
+        assert_eq!(
+            manager.state,
+            LifecycleState::AwaitingBeacon { timestamp: 100 }
+        );
+
+        manager.messages.push_back(super::structs::Item {
+            request: "{}".to_string(),
+            fetch_id: 0,
+        });
+        // held inputs are not consumed while waiting the beacon
+        assert!(manager.consume_input().is_none());
+
+        assert!(manager.apply(LifecycleEvent::Beacon { timestamp: 103 }));
+        assert_eq!(manager.state, LifecycleState::Draining);
+        assert!(manager.consume_input().is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_app_state_status_for_rollup() {
+        let app = create_app_state();
+        let advance = |input_index: u64| {
+            let metadata = RollupInputDataMetadataBuilder::default()
+                .with_input_index(input_index.into())
+                .build();
+            RollupInput::builder()
+                .with_metadata(metadata)
+                .with_request_type(RollupState::Advance)
+                .build()
+        };
+        let reject = RequestRollups {
+            status: "reject".to_string(),
+        };
+        let accept = RequestRollups {
+            status: "accept".to_string(),
+        };
+
+        // the DApp processes the last input fetched, its status goes to the rollup server
+        let fetch_id = app.fetched().await;
+        app.dispatched(fetch_id).await;
+        app.start_input(&advance(5)).await;
+        assert_eq!(
+            app.status_for_rollup(&reject).await,
+            RollupStatus {
+                status: "reject",
+                held_reject: None
+            }
+        );
+
+        // fetching the inputs 6 and 7 while holding accepted the input 5
+        app.fetched().await;
+        app.store_input(&advance(6)).await;
+        app.fetched().await;
+        app.store_input(&advance(7)).await;
+        assert_eq!(
+            app.status_for_rollup(&reject).await,
+            RollupStatus {
+                status: "accept",
+                held_reject: Some(5)
+            }
+        );
+
+        // and fetching the input 7 accepted the input 6
+        assert!(app.consume_input().await.is_some());
+        app.start_input(&advance(6)).await;
+        assert_eq!(
+            app.status_for_rollup(&reject).await,
+            RollupStatus {
+                status: "accept",
+                held_reject: Some(6)
+            }
+        );
+        assert_eq!(
+            app.status_for_rollup(&accept).await,
+            RollupStatus {
+                status: "accept",
+                held_reject: None
+            }
+        );
+
+        // the input 7 still waits its status on the rollup server
+        assert!(app.consume_input().await.is_some());
+        app.start_input(&advance(7)).await;
+        assert_eq!(
+            app.status_for_rollup(&reject).await,
+            RollupStatus {
+                status: "reject",
+                held_reject: None
+            }
+        );
+    }
 }
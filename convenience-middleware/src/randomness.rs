@@ -0,0 +1,128 @@
+use std::error::Error;
+
+use sha3::{
+    digest::{ExtendableOutput, XofReader},
+    Digest, Keccak256, Sha3_256, Shake256, Shake256Reader,
+};
+
+use crate::models::structs::{DerivationScheme, Draw};
+
+/**
+ * Upper bound of a single /random/bytes response
+ */
+pub const MAX_RANDOM_BYTES: usize = 64 * 1024;
+
+/**
+ * Upper bound of the numbers returned by /random/range and of the size of a /random/shuffle
+ */
+pub const MAX_RANDOM_ITEMS: usize = 4096;
+
+/**
+ * Derive the seed of a draw from the verified beacon randomness.
+ * Anyone holding the beacon of the round can recompute it:
+ *
+ * SHA3-256(randomness || round || input_index || sequence || domain)
+ *
+ * randomness is the 32 bytes derived from the beacon signature,
+ * round, input_index and sequence are big-endian u64 and domain is the raw utf-8 string.
+ *
+ * The keccak256-packed scheme hashes the same bytes with Keccak-256, so a contract gets the seed with
+ * keccak256(abi.encodePacked(bytes32 randomness, uint64 round, uint64 inputIndex, uint64 sequence, string domain))
+ */
+pub fn derive_seed(
+    scheme: DerivationScheme,
+    beacon_randomness: &str,
+    draw: &Draw,
+) -> Result<[u8; 32], Box<dyn Error>> {
+    let randomness = hex::decode(beacon_randomness.trim_start_matches("0x"))?;
+
+    match scheme {
+        DerivationScheme::Sha3_256 => Ok(hash_draw::<Sha3_256>(&randomness, draw)),
+        DerivationScheme::Keccak256Packed => Ok(hash_draw::<Keccak256>(&randomness, draw)),
+    }
+}
+
+fn hash_draw<D: Digest>(randomness: &[u8], draw: &Draw) -> [u8; 32] {
+    let mut hasher = D::new();
+    hasher.update(randomness);
+    hasher.update(draw.round.to_be_bytes());
+    hasher.update(draw.input_index.to_be_bytes());
+    hasher.update(draw.sequence.to_be_bytes());
+    hasher.update(draw.domain.as_bytes());
+
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&hasher.finalize());
+    seed
+}
+
+/**
+ * Stream of bytes expanded from a draw seed with the SHAKE256 extendable-output function
+ */
+pub struct SeedStream {
+    reader: Shake256Reader,
+}
+
+impl SeedStream {
+    pub fn new(seed: &[u8; 32]) -> Self {
+        let mut shake = Shake256::default();
+        sha3::digest::Update::update(&mut shake, seed);
+        Self {
+            reader: shake.finalize_xof(),
+        }
+    }
+
+    pub fn fill(&mut self, bytes: &mut [u8]) {
+        self.reader.read(bytes);
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill(&mut bytes);
+        u64::from_be_bytes(bytes)
+    }
+
+    /**
+     * Uniform number in [min, max] (inclusive).
+     * Values from the biased tail of the u64 space are rejected instead of reduced with modulo.
+     */
+    pub fn next_in_range(&mut self, min: u64, max: u64) -> u64 {
+        let span = u128::from(max - min) + 1;
+        let zone = (1u128 << 64) / span * span;
+        loop {
+            let value = u128::from(self.next_u64());
+            if value < zone {
+                return min + (value % span) as u64;
+            }
+        }
+    }
+}
+
+/**
+ * Expand a draw seed into an arbitrary number of bytes
+ */
+pub fn expand_seed(seed: &[u8; 32], length: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; length];
+    SeedStream::new(seed).fill(&mut bytes);
+    bytes
+}
+
+/**
+ * Draw count uniform numbers in [min, max] from a draw seed
+ */
+pub fn sample_range(seed: &[u8; 32], min: u64, max: u64, count: usize) -> Vec<u64> {
+    let mut stream = SeedStream::new(seed);
+    (0..count).map(|_| stream.next_in_range(min, max)).collect()
+}
+
+/**
+ * Fisher-Yates permutation of 0..n from a draw seed
+ */
+pub fn shuffle(seed: &[u8; 32], n: usize) -> Vec<u64> {
+    let mut stream = SeedStream::new(seed);
+    let mut items: Vec<u64> = (0..n as u64).collect();
+    for i in (1..items.len()).rev() {
+        let j = stream.next_in_range(0, i as u64) as usize;
+        items.swap(i, j);
+    }
+    items
+}
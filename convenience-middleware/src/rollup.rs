@@ -1,21 +1,26 @@
 pub mod server {
-    use dotenvy::var;
-    use hyper::{Body, Response};
+    use hyper::{body::Bytes, Body, Response, StatusCode};
     use log::info;
     use serde_json::{json, Value};
     use std::error::Error;
 
     use super::input::RollupInput;
+    use crate::{
+        models::structs::{BeaconInputReport, Provenance},
+        utils::util::generate_payload_hex,
+    };
 
-    pub async fn send_finish(status: &str) -> Result<Response<Body>, Box<dyn Error>> {
-        let server_str = var("ROLLUP_HTTP_SERVER_URL").expect("Env is not set");
-        info!("Sending finish to {}", &server_str);
+    pub async fn send_finish(
+        server_addr: &str,
+        status: &str,
+    ) -> Result<Response<Body>, Box<dyn Error>> {
+        info!("Sending finish to {}", server_addr);
         let client = hyper::Client::new();
         let response = json!({"status" : status});
         let request = hyper::Request::builder()
             .method(hyper::Method::POST)
             .header(hyper::header::CONTENT_TYPE, "application/json")
-            .uri(format!("{}/finish", &server_str))
+            .uri(format!("{}/finish", server_addr))
             .body(hyper::Body::from(response.to_string()))?;
 
         let response = client.request(request).await?;
@@ -28,9 +33,10 @@ pub mod server {
     }
 
     pub async fn send_finish_and_retrieve_input(
+        server_addr: &str,
         status: &str,
     ) -> Result<RollupInput, Box<dyn Error>> {
-        let response = send_finish(status).await?;
+        let response = send_finish(server_addr, status).await?;
 
         if response.status() == hyper::StatusCode::ACCEPTED {
             return Err("Skip".into());
@@ -41,9 +47,10 @@ pub mod server {
         Ok(result)
     }
 
-    pub async fn send_report(report: Value) -> Result<&'static str, Box<dyn std::error::Error>> {
-        let server_addr =
-            var("ROLLUP_HTTP_SERVER_URL").expect("ROLLUP_HTTP_SERVER_URL is not set");
+    pub async fn send_report(
+        server_addr: &str,
+        report: Value,
+    ) -> Result<&'static str, Box<dyn std::error::Error>> {
         let client = hyper::Client::new();
         let req = hyper::Request::builder()
             .method(hyper::Method::POST)
@@ -54,6 +61,118 @@ pub mod server {
         let _ = client.request(req).await?;
         Ok("accept")
     }
+
+    pub async fn send_notice(server_addr: &str, notice: Value) -> Result<(), Box<dyn Error>> {
+        let client = hyper::Client::new();
+        let req = hyper::Request::builder()
+            .method(hyper::Method::POST)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .uri(format!("{}/notice", server_addr))
+            .body(hyper::Body::from(notice.to_string()))?;
+
+        let response = client.request(req).await?;
+        info!(
+            "Received notice status {} from RollupServer",
+            response.status()
+        );
+        Ok(())
+    }
+
+    /**
+     * Record on L1 the beacons and derivation inputs behind the draws, so players can verify them
+     */
+    pub async fn send_provenance_notice(
+        server_addr: &str,
+        draws: &[Provenance],
+    ) -> Result<(), Box<dyn Error>> {
+        let payload = generate_payload_hex(json!({ "drand_provenance": draws }))?;
+        send_notice(server_addr, json!({ "payload": payload })).await
+    }
+
+    /**
+     * Forward an output of the DApp (notice, voucher, report or exception) to the rollup server,
+     * returning its status and body untouched
+     */
+    pub async fn forward_output(
+        server_addr: &str,
+        kind: &str,
+        output: Bytes,
+    ) -> Result<(StatusCode, Bytes), Box<dyn Error>> {
+        let client = hyper::Client::new();
+        let req = hyper::Request::builder()
+            .method(hyper::Method::POST)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .uri(format!("{}/{}", server_addr, kind))
+            .body(hyper::Body::from(output))?;
+
+        let response = client.request(req).await?;
+        let status = response.status();
+        #[allow(deprecated)]
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        Ok((status, body))
+    }
+
+    /**
+     * Tell the DApp clients which held input produced an output, since the rollup server
+     * attributes it to the last input we fetched. `index` is the one the rollup server gave the output, if any
+     */
+    pub async fn send_held_output_report(
+        server_addr: &str,
+        kind: &str,
+        input_index: u64,
+        index: Option<u64>,
+    ) -> Result<(), Box<dyn Error>> {
+        let payload = generate_payload_hex(json!({
+            "held_input_output": {
+                "kind": kind,
+                "input_index": input_index,
+                "index": index,
+            }
+        }))?;
+        send_report(server_addr, json!({ "payload": payload })).await?;
+        Ok(())
+    }
+
+    /**
+     * Tell the DApp clients that a held input was rejected after the rollup server accepted it,
+     * since we had to finish it to fetch the newer inputs
+     */
+    pub async fn send_held_reject_report(
+        server_addr: &str,
+        input_index: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let payload = generate_payload_hex(json!({ "held_input_rejected": input_index }))?;
+        send_report(server_addr, json!({ "payload": payload })).await?;
+        Ok(())
+    }
+
+    /**
+     * Tell the provider which beacons of its input were kept, ignored or rejected,
+     * since the DApp never sees the inputs carrying only beacons
+     */
+    pub async fn send_beacon_input_report(
+        server_addr: &str,
+        report: &BeaconInputReport,
+    ) -> Result<(), Box<dyn Error>> {
+        let payload = generate_payload_hex(json!({ "drand_beacons": report }))?;
+        send_report(server_addr, json!({ "payload": payload })).await?;
+        Ok(())
+    }
+
+    /**
+     * Tell the sender of a drand config input why the middleware did not apply it
+     */
+    pub async fn send_drand_config_rejected_report(
+        server_addr: &str,
+        input_index: Option<u64>,
+        cause: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let payload = generate_payload_hex(json!({
+            "drand_config_rejected": { "input_index": input_index, "cause": cause }
+        }))?;
+        send_report(server_addr, json!({ "payload": payload })).await?;
+        Ok(())
+    }
 }
 
 pub mod input {
@@ -62,8 +181,9 @@ pub mod input {
         utils::util::{deserialize_obj, generate_payload_hex},
     };
     use hyper::{Body, Response};
+    use log::warn;
     use serde::{Deserialize, Serialize};
-    use serde_json::json;
+    use serde_json::{json, Value};
     use std::error::Error;
 
     #[derive(Default, Debug)]
@@ -117,6 +237,11 @@ pub mod input {
     pub struct RollupInput {
         pub data: RollupInputData,
         pub request_type: RollupState,
+        /**
+         * Body as the rollup server sent it, the DApp receives it unchanged
+         */
+        #[serde(skip)]
+        pub raw: String,
     }
 
     #[derive(Default)]
@@ -142,6 +267,7 @@ pub mod input {
             RollupInput {
                 data: self.0.data,
                 request_type: self.0.request_type,
+                raw: String::new(),
             }
         }
     }
@@ -150,7 +276,9 @@ pub mod input {
         type Error = serde_json::Error;
 
         fn try_from(item: Item) -> Result<Self, Self::Error> {
-            serde_json::from_str(&item.request)
+            let mut input: RollupInput = serde_json::from_str(&item.request)?;
+            input.raw = item.request;
+            Ok(input)
         }
     }
 
@@ -165,14 +293,42 @@ pub mod input {
         pub fn builder() -> RollupInputBuilder {
             RollupInputBuilder::default()
         }
+
+        /**
+         * Body for the DApp, the one the rollup server sent when the input came from it
+         */
+        pub fn raw_body(&self) -> String {
+            match self.raw.is_empty() {
+                true => serde_json::to_string(self).unwrap_or_default(),
+                false => self.raw.clone(),
+            }
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug)]
     pub struct RollupInputData {
         pub payload: String,
+        #[serde(default, deserialize_with = "lenient_metadata")]
         pub metadata: Option<RollupInputDataMetadata>,
     }
 
+    /**
+     * Metadata the middleware cannot read is left out, the input still reaches the DApp as it was sent
+     */
+    fn lenient_metadata<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<RollupInputDataMetadata>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let metadata = Option::<Value>::deserialize(deserializer)?;
+        Ok(metadata.and_then(|metadata| {
+            serde_json::from_value(metadata)
+                .map_err(|e| warn!("Ignoring the unreadable metadata of the input: {}", e))
+                .ok()
+        }))
+    }
+
     impl Default for RollupInputData {
         fn default() -> Self {
             let payload = generate_payload_hex(json!({"input":"0x00"})).unwrap();
@@ -184,53 +340,29 @@ pub mod input {
         }
     }
 
-    type BigIntLike = u128;
-
-    #[derive(Serialize, Deserialize, Debug, Default)]
-    pub struct RollupInputDataMetadata {
-        // #[serde(with = "ethnum::serde::prefixed")]
-        // pub block_number: BigIntLike,
-        // #[serde(with = "ethnum::serde::prefixed")]
-        // pub epoch_index: BigIntLike,
-        // #[serde(with = "ethnum::serde::prefixed")]
-        // pub input_index: BigIntLike,
-        // #[serde(with = "ethnum::serde::prefixed")]
-        // pub msg_sender: BigIntLike,
-        // #[serde(with = "ethnum::serde::prefixed")]
-        // pub timestamp: BigIntLike,
-        pub block_number: u128,
-        pub epoch_index: u128,
-        pub input_index: u128,
-        pub msg_sender: String,
-        pub timestamp: u64,
-    }
-
-    impl RollupInputDataMetadata {
-        pub fn builder() -> RollupInputDataMetadataBuilder {
-            RollupInputDataMetadataBuilder::default()
-        }
-    }
+    pub use cartesi_rollup_metadata::Metadata as RollupInputDataMetadata;
+    use cartesi_rollup_metadata::{Address, U256};
 
     #[derive(Default)]
     pub struct RollupInputDataMetadataBuilder(RollupInputDataMetadata);
 
     impl RollupInputDataMetadataBuilder {
-        pub fn with_block_number(mut self, block_number: BigIntLike) -> Self {
+        pub fn with_block_number(mut self, block_number: U256) -> Self {
             self.0.block_number = block_number;
             self
         }
 
-        pub fn with_epoch_index(mut self, epoch_index: BigIntLike) -> Self {
-            self.0.epoch_index = epoch_index;
+        pub fn with_epoch_index(mut self, epoch_index: U256) -> Self {
+            self.0.epoch_index = Some(epoch_index);
             self
         }
 
-        pub fn with_input_index(mut self, input_index: BigIntLike) -> Self {
+        pub fn with_input_index(mut self, input_index: U256) -> Self {
             self.0.input_index = input_index;
             self
         }
 
-        pub fn with_address_sender(mut self, msg_sender: String) -> Self {
+        pub fn with_address_sender(mut self, msg_sender: Address) -> Self {
             self.0.msg_sender = msg_sender;
             self
         }
@@ -249,11 +381,16 @@ pub mod input {
         pub async fn try_from_async(response: Response<Body>) -> Result<Self, Box<dyn Error>> {
             let body = hyper::body::to_bytes(response).await?;
             let utf = std::str::from_utf8(&body)?;
-            let result_deserialization = serde_json::from_str::<RollupInput>(utf)?;
+            let mut result_deserialization = serde_json::from_str::<RollupInput>(utf)?;
+            result_deserialization.raw = utf.to_string();
             Ok(result_deserialization)
         }
     }
 
+    /**
+     * Check if the payload carries an input for the DApp.
+     * Beacons sent without an input, or with the placeholder "0x00", are only for the middleware.
+     */
     pub fn has_input_inside_input(input: &RollupInput) -> bool {
         let json = input.data.payload.trim_start_matches("0x");
         let json = match hex::decode(json) {
@@ -269,6 +406,13 @@ pub mod input {
             Some(json) => json,
             None => return false,
         };
-        value.contains_key("input")
+        let carries_beacon = value.contains_key("beacon") || value.contains_key("beacons");
+        match value.get("input") {
+            Some(Value::String(input)) if carries_beacon => {
+                !matches!(input.as_str(), "" | "0x" | "0x00")
+            }
+            Some(_) => true,
+            None => false,
+        }
     }
 }
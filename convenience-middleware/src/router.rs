@@ -1,16 +1,36 @@
 pub mod routes {
-    use actix_web::{get, post, put, web, HttpResponse, Responder, ResponseError};
-    use log::{error, info};
+    use actix_web::{
+        get,
+        http::{header::ContentType, StatusCode},
+        post, put,
+        web::{self, Bytes},
+        HttpResponse, Responder, ResponseError,
+    };
+    use log::{error, info, warn};
 
     use crate::{
-        drand::{get_drand_beacon, is_querying_pending_beacon, send_pending_beacon_report},
+        drand::{
+            drand_env_from_chain_info, get_drand_beacons, get_drand_config_update,
+            has_drand_config, is_querying_pending_beacon, is_querying_status,
+            send_pending_beacon_report, send_status_report, validate_drand_chain,
+        },
         errors::CheckerError,
-        models::structs::{AppState, DrandEnv, RequestRollups, Timestamp},
+        models::structs::{
+            AppState, BeaconInputReport, ByteFormat, ChainInfo, ChainRotationQuery, DrandEnv,
+            ProvenanceMode, RandomBytesQuery, RandomQuery, RandomSpec, RangeQuery, RequestRollups,
+            Round, ShuffleQuery, Timestamp, TlockDecryptRequest,
+        },
+        randomness::{expand_seed, sample_range, shuffle, MAX_RANDOM_BYTES, MAX_RANDOM_ITEMS},
         rollup::{
-            input::{has_input_inside_input, RollupInput},
-            server::send_finish_and_retrieve_input,
+            input::{has_input_inside_input, RollupInput, RollupState},
+            server::{
+                forward_output, send_beacon_input_report, send_drand_config_rejected_report,
+                send_finish_and_retrieve_input, send_held_output_report, send_held_reject_report,
+                send_provenance_notice,
+            },
         },
-        utils::util::{load_env_from_memory, write_env_to_json},
+        tlock,
+        utils::util::write_env_to_json,
     };
 
     #[put("/update_drand_config")]
@@ -23,24 +43,99 @@ pub mod routes {
             ctx.version
         );
 
-        let _ = ctx.input_buffer_manager.lock().await;
+        check_config_has_no_owner(&ctx)?;
+        apply_drand_config(&ctx, body.into_inner()).await?;
+        Ok::<_, CheckerError>(HttpResponse::NoContent().finish())
+    }
 
-        let drand = body.into_inner();
+    #[put("/update_drand_chain_info")]
+    async fn update_drand_chain_info(
+        ctx: web::Data<AppState>,
+        query: web::Query<ChainRotationQuery>,
+        body: web::Json<ChainInfo>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        info!(
+            "Received update_drand_chain_info request from DApp for chain {} version={}",
+            body.hash, ctx.version
+        );
 
-        load_env_from_memory(drand).await;
+        check_config_has_no_owner(&ctx)?;
+        let mut drand = drand_env_from_chain_info(&body)?;
+        drand.DRAND_EFFECTIVE_TIME = query.effective_time;
+        apply_drand_config(&ctx, drand).await?;
+        Ok::<_, CheckerError>(HttpResponse::NoContent().finish())
+    }
+
+    /**
+     * Once a config owner is set, the config is only updated through its advance inputs
+     */
+    fn check_config_has_no_owner(ctx: &AppState) -> Result<(), CheckerError> {
+        match ctx.config_owner {
+            Some(owner) => Err(CheckerError::UnauthorizedDrandConfig {
+                cause: format!("the config is updated by advance inputs of {}", owner),
+            }),
+            None => Ok(()),
+        }
+    }
 
-        let result = write_env_to_json().await;
+    /**
+     * Apply the drand config sent as an advance input, reporting why it was refused
+     */
+    async fn update_drand_config_from_input(ctx: &AppState, rollup_input: &RollupInput) {
+        let result = match get_drand_config_update(ctx.config_owner.as_ref(), rollup_input) {
+            Some(Ok(drand)) => apply_drand_config(ctx, drand).await,
+            Some(Err(e)) => Err(e),
+            None => return,
+        };
+        match result {
+            Ok(()) => info!("Drand config updated by the config owner"),
+            Err(e) => {
+                warn!("Drand config update refused: {}", e);
+                let input_index = rollup_input
+                    .data
+                    .metadata
+                    .as_ref()
+                    .map(|metadata| metadata.input_index_u64());
+                if let Err(e) =
+                    send_drand_config_rejected_report(&ctx.rollup_url, input_index, &e.to_string())
+                        .await
+                {
+                    error!("Error sending drand config rejected report: {}", e);
+                }
+            }
+        }
+    }
 
-        // maybe can generate a error on write json but
-        // already change the env in memory
-        if let Err(e) = result {
-            error!("Error updating drand config: {}", e);
-            return Err(CheckerError::InvalidDrandConfig {
-                cause: e.to_string(),
-            });
+    /**
+     * Schedule or replace the drand chain, the beacons already kept stay on the chain that verified them
+     */
+    async fn apply_drand_config(ctx: &AppState, drand: DrandEnv) -> Result<(), CheckerError> {
+        if let Some(effective_time) = drand.DRAND_EFFECTIVE_TIME {
+            if let Some(last) = ctx.last_beacon_timestamp().await {
+                if effective_time <= last {
+                    return Err(CheckerError::InvalidDrandConfig {
+                        field: "DRAND_EFFECTIVE_TIME".to_string(),
+                        cause: format!("must be after the last beacon at {}", last),
+                    });
+                }
+            }
         }
 
-        Ok(HttpResponse::NoContent().finish())
+        let config = ctx.drand_config().updated(&drand);
+        validate_drand_chain(config.current_chain())?;
+
+        if let Some(path) = &ctx.drand_config_path {
+            if let Err(e) = write_env_to_json(path, &config.to_env()).await {
+                error!("Error updating drand config: {}", e);
+                return Err(CheckerError::InvalidDrandConfig {
+                    field: "drand.config.json".to_string(),
+                    cause: e.to_string(),
+                });
+            }
+        }
+        ctx.set_drand_config(config);
+
+        Ok(())
     }
 
     #[post("/finish")]
@@ -53,110 +148,573 @@ pub mod routes {
             body, ctx.version
         );
 
-        // the DApp consume from the buffer first
-        if let Some(item) = ctx.consume_input().await {
-            let request = item.request.clone();
-            match RollupInput::try_from(item) {
-                Ok(input) => {
-                    if has_input_inside_input(&input) {
-                        return HttpResponse::Ok().body(request);
-                    } else {
-                        return HttpResponse::Accepted().finish();
-                    }
-                }
+        // the notice must reach the rollup server before we finish the input
+        send_provenance(&ctx, ProvenanceMode::Input).await;
 
-                Err(_) => return HttpResponse::Accepted().finish(),
+        let status = ctx.status_for_rollup(&body).await;
+        if let Some(input_index) = status.held_reject {
+            warn!(
+                "The DApp rejected the held input {} already accepted by the rollup server",
+                input_index
+            );
+            if let Err(e) = send_held_reject_report(&ctx.rollup_url, input_index).await {
+                error!("Error sending held reject report: {}", e);
             }
         }
-        let rollup_input = match send_finish_and_retrieve_input("accept").await {
-            Ok(input) => input,
-            Err(_) => return HttpResponse::Accepted().finish(),
-        };
+
+        // the DApp consume from the buffer first
+        if let Some(response) = dispatch_buffered_input(&ctx).await {
+            return response;
+        }
+        let was_holding = ctx.is_holding().await;
+        ctx.metrics.finish_roundtrips.inc();
+        let rollup_input =
+            match send_finish_and_retrieve_input(&ctx.rollup_url, status.status).await {
+                Ok(input) => input,
+                Err(_) => return HttpResponse::Accepted().finish(),
+            };
+        let fetch_id = ctx.fetched().await;
         match rollup_input.request_type.as_str() {
             "advance_state" => {
-                ctx.set_inspecting(false).await;
-                if let Ok(beacon) = get_drand_beacon(&rollup_input.data.payload) {
-                    info!("Is Drand!!! {:?}", beacon);
-                    ctx.keep_newest_beacon(beacon);
+                // a config update waits for the inputs held before it
+                if !was_holding {
+                    update_drand_config_from_input(&ctx, &rollup_input).await;
+                }
+                if let Some(beacons) = keep_input_beacons(&ctx, &rollup_input).await {
+                    info!("Is Drand!!! {:?}", beacons);
+                    report_beacon_only_input(&ctx, &rollup_input, &beacons).await;
                 }
             }
             "inspect_state" => {
-                ctx.set_inspecting(true).await;
                 if is_querying_pending_beacon(&rollup_input).unwrap() {
                     send_pending_beacon_report(&ctx).await;
 
                     // This is a specific inspect, so we omit it from the DApp
                     return HttpResponse::Accepted().finish();
                 }
+                if is_querying_status(&rollup_input).unwrap_or(false) {
+                    send_status_report(&ctx).await;
+                    return HttpResponse::Accepted().finish();
+                }
             }
             &_ => {
                 error!("Unknown request type");
             }
         };
 
+        if was_holding {
+            // keep the order of the held inputs, this one goes after them
+            if has_input_inside_input(&rollup_input) || has_drand_config(&rollup_input) {
+                ctx.store_input(&rollup_input).await;
+            }
+            return dispatch_buffered_input(&ctx)
+                .await
+                .unwrap_or_else(|| HttpResponse::Accepted().finish());
+        }
+
         // Dispatch the input to the DApp
         if has_input_inside_input(&rollup_input) {
-            HttpResponse::Ok().json(rollup_input)
+            ctx.dispatched(fetch_id).await;
+            ctx.start_input(&rollup_input).await;
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .body(rollup_input.raw_body())
         } else {
             HttpResponse::Accepted().finish()
         }
     }
 
+    /**
+     * Dispatch the oldest held input, None when there is nothing to dispatch or we are still holding.
+     * A held config update is applied now, once the inputs before it are done.
+     */
+    async fn dispatch_buffered_input(ctx: &web::Data<AppState>) -> Option<HttpResponse> {
+        let item = ctx.consume_input().await?;
+        let request = item.request.clone();
+        let input = match RollupInput::try_from(item) {
+            Ok(input) => input,
+            Err(_) => return Some(HttpResponse::Accepted().finish()),
+        };
+        if matches!(input.request_type, RollupState::Advance) {
+            update_drand_config_from_input(ctx, &input).await;
+        }
+        if !has_input_inside_input(&input) {
+            return Some(HttpResponse::Accepted().finish());
+        }
+        ctx.start_input(&input).await;
+        Some(
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .body(request),
+        )
+    }
+
+    #[post("/notice")]
+    async fn notice(ctx: web::Data<AppState>, body: Bytes) -> impl Responder {
+        forward(&ctx, "notice", body).await
+    }
+
+    #[post("/voucher")]
+    async fn voucher(ctx: web::Data<AppState>, body: Bytes) -> impl Responder {
+        forward(&ctx, "voucher", body).await
+    }
+
+    #[post("/report")]
+    async fn report(ctx: web::Data<AppState>, body: Bytes) -> impl Responder {
+        forward(&ctx, "report", body).await
+    }
+
+    #[post("/exception")]
+    async fn exception(ctx: web::Data<AppState>, body: Bytes) -> impl Responder {
+        forward(&ctx, "exception", body).await
+    }
+
+    /**
+     * Forward an output of the DApp to the rollup server and answer with the rollup server response
+     */
+    async fn forward(ctx: &web::Data<AppState>, kind: &str, body: Bytes) -> HttpResponse {
+        let owner = ctx.output_owner().await;
+        match owner.input_index {
+            Some(input_index) => info!(
+                "Received {} from DApp for input {} version={}",
+                kind, input_index, ctx.version
+            ),
+            None => info!(
+                "Received {} from DApp for inspect version={}",
+                kind, ctx.version
+            ),
+        }
+        let held = owner.input_index.filter(|_| owner.buffered);
+        if let Some(input_index) = held {
+            warn!(
+                "The DApp sent a {} for the held input {}, the rollup server attributes it to the last input fetched",
+                kind, input_index
+            );
+            // the rollup server may stop taking outputs after an exception, so its report goes first
+            if kind == "exception" {
+                report_held_output(ctx, kind, input_index, None).await;
+            }
+        }
+
+        match forward_output(&ctx.rollup_url, kind, body).await {
+            Ok((status, body)) => {
+                if let Some(input_index) =
+                    held.filter(|_| kind != "exception" && status.is_success())
+                {
+                    let index = serde_json::from_slice::<serde_json::Value>(&body)
+                        .ok()
+                        .and_then(|body| body["index"].as_u64());
+                    report_held_output(ctx, kind, input_index, index).await;
+                }
+                let status = StatusCode::from_u16(status.as_u16())
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                HttpResponse::build(status)
+                    .content_type(ContentType::json())
+                    .body(body)
+            }
+            Err(e) => {
+                error!("Error forwarding {} to the rollup server: {}", kind, e);
+                HttpResponse::BadGateway().finish()
+            }
+        }
+    }
+
+    async fn report_held_output(
+        ctx: &web::Data<AppState>,
+        kind: &str,
+        input_index: u64,
+        index: Option<u64>,
+    ) {
+        if let Err(e) = send_held_output_report(&ctx.rollup_url, kind, input_index, index).await {
+            error!(
+                "Error reporting the {} of the held input {}: {}",
+                kind, input_index, e
+            );
+        }
+    }
+
+    #[get("/status")]
+    async fn middleware_status(ctx: web::Data<AppState>) -> impl Responder {
+        HttpResponse::Ok().json(ctx.status().await)
+    }
+
+    #[get("/metrics")]
+    async fn metrics(ctx: web::Data<AppState>) -> impl Responder {
+        HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(ctx.render_metrics().await)
+    }
+
+    #[get("/round_for_timestamp")]
+    async fn round_for_timestamp(
+        ctx: web::Data<AppState>,
+        query: web::Query<Timestamp>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        // the same round used by /random?timestamp=
+        let round = ctx.safe_round_for_timestamp(query.timestamp)?;
+        Ok::<_, CheckerError>(HttpResponse::Ok().json(round))
+    }
+
+    #[get("/timestamp_for_round")]
+    async fn timestamp_for_round(
+        ctx: web::Data<AppState>,
+        query: web::Query<Round>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        let round = ctx.round_timestamp(query.round)?;
+        Ok::<_, CheckerError>(HttpResponse::Ok().json(round))
+    }
+
     #[get("/random")]
     async fn request_random(
         ctx: web::Data<AppState>,
-        query: web::Query<Timestamp>,
+        query: web::Query<RandomQuery>,
     ) -> Result<impl Responder, impl ResponseError> {
         info!(
-            "Received random request from DApp timestamp={} version={}",
-            query.timestamp, ctx.version
+            "Received random request from DApp timestamp={:?} round={:?} version={}",
+            query.timestamp, query.round, ctx.version
         );
-        let randomness: Option<String> = ctx.get_randomness_for_timestamp(query.timestamp);
-        if let Some(randomness) = randomness {
-            // we already have the randomness to continue the process
-            return Ok(HttpResponse::Ok().body(randomness));
+        let randomness = hold_randomness(&ctx, &query).await?;
+        Ok::<_, CheckerError>(HttpResponse::Ok().body(hex::encode(randomness)))
+    }
+
+    #[get("/random/bytes")]
+    async fn request_random_bytes(
+        ctx: web::Data<AppState>,
+        query: web::Query<RandomQuery>,
+        bytes_query: web::Query<RandomBytesQuery>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        info!(
+            "Received random bytes request from DApp timestamp={:?} round={:?} length={} version={}",
+            query.timestamp, query.round, bytes_query.length, ctx.version
+        );
+        if bytes_query.length > MAX_RANDOM_BYTES {
+            return Err(CheckerError::InvalidRandomQuery {
+                cause: format!("length must be at most {}", MAX_RANDOM_BYTES),
+            });
+        }
+        let randomness = hold_randomness(&ctx, &query).await?;
+        let bytes = expand_seed(&randomness, bytes_query.length);
+
+        match bytes_query.format {
+            ByteFormat::Hex => Ok(HttpResponse::Ok().body(hex::encode(bytes))),
+            ByteFormat::Raw => Ok(HttpResponse::Ok()
+                .content_type(ContentType::octet_stream())
+                .body(bytes)),
+        }
+    }
+
+    #[get("/random/range")]
+    async fn request_random_range(
+        ctx: web::Data<AppState>,
+        query: web::Query<RandomQuery>,
+        range_query: web::Query<RangeQuery>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        info!(
+            "Received random range request from DApp timestamp={:?} round={:?} min={} max={} count={} version={}",
+            query.timestamp, query.round, range_query.min, range_query.max, range_query.count, ctx.version
+        );
+        if range_query.min > range_query.max {
+            return Err(CheckerError::InvalidRandomQuery {
+                cause: "min must not be greater than max".to_string(),
+            });
+        }
+        if range_query.count > MAX_RANDOM_ITEMS {
+            return Err(CheckerError::InvalidRandomQuery {
+                cause: format!("count must be at most {}", MAX_RANDOM_ITEMS),
+            });
+        }
+        let randomness = hold_randomness(&ctx, &query).await?;
+        let numbers = sample_range(
+            &randomness,
+            range_query.min,
+            range_query.max,
+            range_query.count,
+        );
+        Ok(HttpResponse::Ok().json(numbers))
+    }
+
+    #[get("/random/shuffle")]
+    async fn request_random_shuffle(
+        ctx: web::Data<AppState>,
+        query: web::Query<RandomQuery>,
+        shuffle_query: web::Query<ShuffleQuery>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        info!(
+            "Received random shuffle request from DApp timestamp={:?} round={:?} n={} version={}",
+            query.timestamp, query.round, shuffle_query.n, ctx.version
+        );
+        if shuffle_query.n > MAX_RANDOM_ITEMS {
+            return Err(CheckerError::InvalidRandomQuery {
+                cause: format!("n must be at most {}", MAX_RANDOM_ITEMS),
+            });
+        }
+        let randomness = hold_randomness(&ctx, &query).await?;
+        Ok(HttpResponse::Ok().json(shuffle(&randomness, shuffle_query.n)))
+    }
+
+    #[post("/random/batch")]
+    async fn request_random_batch(
+        ctx: web::Data<AppState>,
+        specs: web::Json<Vec<RandomSpec>>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        info!(
+            "Received random batch request from DApp with {} specs version={}",
+            specs.len(),
+            ctx.version
+        );
+        for spec in specs.iter() {
+            check_random_query(&spec.query)?;
+            check_round_not_too_old(&ctx, &spec.query).await?;
         }
-        if ctx.is_inspecting() {
+        let total: usize = specs.iter().map(|spec| spec.count).sum();
+        if total > MAX_RANDOM_ITEMS {
+            return Err(CheckerError::InvalidRandomQuery {
+                cause: format!("the batch must ask at most {} draws", MAX_RANDOM_ITEMS),
+            });
+        }
+
+        let batch = hold_until(&ctx, async |ctx| ctx.get_batch_randomness(&specs).await).await?;
+        let batch: Vec<Vec<String>> = batch
+            .iter()
+            .map(|draws| draws.iter().map(hex::encode).collect())
+            .collect();
+        Ok(HttpResponse::Ok().json(batch))
+    }
+
+    #[post("/tlock/decrypt")]
+    async fn tlock_decrypt(
+        ctx: web::Data<AppState>,
+        body: web::Json<TlockDecryptRequest>,
+    ) -> Result<impl Responder, impl ResponseError> {
+        info!(
+            "Received tlock decrypt request from DApp for round {} version={}",
+            body.round, ctx.version
+        );
+        let ciphertext = hex::decode(body.ciphertext.trim_start_matches("0x")).map_err(|e| {
+            CheckerError::InvalidTlockCiphertext {
+                cause: e.to_string(),
+            }
+        })?;
+        ctx.round_timestamp(body.round)?;
+        if ctx.is_round_too_old(body.round).await {
+            return Err(CheckerError::RoundTooOld { round: body.round });
+        }
+
+        let beacon =
+            hold_until(&ctx, async |ctx| ctx.get_beacon_of_round(body.round).await).await?;
+        let scheme = ctx.drand_config().chain_at(beacon.timestamp).scheme;
+        let message =
+            tlock::decrypt(scheme, &beacon.signature, &ciphertext).map_err(|e| match e
+                .downcast::<CheckerError>()
+            {
+                Ok(e) => *e,
+                Err(e) => CheckerError::InvalidTlockCiphertext {
+                    cause: e.to_string(),
+                },
+            })?;
+        Ok(HttpResponse::Ok().body(hex::encode(message)))
+    }
+
+    fn check_random_query(query: &RandomQuery) -> Result<(), CheckerError> {
+        if query.timestamp.is_some() == query.round.is_some() {
+            return Err(CheckerError::InvalidRandomQuery {
+                cause: "expected either timestamp or round".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /**
+     * Get the randomness for the query, holding the DApp until the beacon arrives
+     */
+    async fn hold_randomness(
+        ctx: &web::Data<AppState>,
+        query: &RandomQuery,
+    ) -> Result<[u8; 32], CheckerError> {
+        check_random_query(query)?;
+        check_round_not_too_old(ctx, query).await?;
+        hold_until(ctx, async |ctx| ctx.get_randomness(query).await).await
+    }
+
+    /**
+     * A round older than the whole beacon history would never be kept, so holding for it would never end.
+     * A round skipped by a newer beacon becomes the pending one and the provider sends it.
+     * A round whose time does not fit in a u64 would never arrive either.
+     */
+    async fn check_round_not_too_old(
+        ctx: &web::Data<AppState>,
+        query: &RandomQuery,
+    ) -> Result<(), CheckerError> {
+        let round = ctx.round_for_query(query)?.round;
+        if ctx.is_round_too_old(round).await {
+            return Err(CheckerError::RoundTooOld { round });
+        }
+        Ok(())
+    }
+
+    /**
+     * Get the value like hold_until_beacon, then send the provenance of its draws
+     */
+    async fn hold_until<T>(
+        ctx: &web::Data<AppState>,
+        get: impl AsyncFn(&AppState) -> Option<T>,
+    ) -> Result<T, CheckerError> {
+        let value = hold_until_beacon(ctx, get).await?;
+        send_provenance(ctx, ProvenanceMode::Draw).await;
+        Ok(value)
+    }
+
+    /**
+     * Send the provenance notices when the configured mode matches the moment,
+     * one per draw for the Draw mode and one with all the draws of the input for the Input mode
+     */
+    async fn send_provenance(ctx: &web::Data<AppState>, moment: ProvenanceMode) {
+        if ctx.provenance_mode != moment {
+            return;
+        }
+        let draws = ctx.take_provenance().await;
+        let notices: Vec<&[_]> = match moment {
+            ProvenanceMode::Draw => draws.chunks(1).collect(),
+            _ if draws.is_empty() => vec![],
+            _ => vec![&draws],
+        };
+        for draws in notices {
+            if let Err(e) = send_provenance_notice(&ctx.rollup_url, draws).await {
+                error!("Error sending provenance notice: {}", e);
+            }
+        }
+    }
+
+    /**
+     * Verify and keep the beacons carried by an advance input, counting the accepted and the rejected beacons.
+     * The verified ones are kept even when others of the same input are rejected.
+     * None when the input carries no beacon.
+     */
+    async fn keep_input_beacons(
+        ctx: &AppState,
+        rollup_input: &RollupInput,
+    ) -> Option<BeaconInputReport> {
+        let results = get_drand_beacons(&ctx.drand_config(), &rollup_input.data.payload).ok()?;
+        let mut beacons = Vec::with_capacity(results.len());
+        let mut rejected = vec![];
+        for result in results {
+            match result {
+                Ok(beacon) => {
+                    ctx.metrics.beacons_accepted.inc();
+                    beacons.push(beacon);
+                }
+                Err(beacon) => {
+                    ctx.metrics.beacons_rejected.inc();
+                    warn!(
+                        "Rejected beacon of round {}: {}",
+                        beacon.round, beacon.cause
+                    );
+                    rejected.push(beacon);
+                }
+            }
+        }
+        let (kept, ignored) = ctx.keep_beacons(beacons).await;
+        let input_index = rollup_input
+            .data
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.input_index_u64());
+        Some(BeaconInputReport {
+            input_index,
+            kept,
+            ignored,
+            rejected,
+        })
+    }
+
+    /**
+     * The DApp never sees an input carrying only beacons, so the provider gets the result in a report
+     */
+    async fn report_beacon_only_input(
+        ctx: &AppState,
+        rollup_input: &RollupInput,
+        beacons: &BeaconInputReport,
+    ) {
+        if has_input_inside_input(rollup_input) {
+            return;
+        }
+        if let Err(e) = send_beacon_input_report(&ctx.rollup_url, beacons).await {
+            error!("Error sending beacon input report: {}", e);
+        }
+    }
+
+    /**
+     * Get the value from the known beacons, or hold the DApp until the next beacon arrives and try again.
+     * A beacon may answer only part of the request, like the oldest round of a batch,
+     * then the DApp is held again for the next missing one.
+     */
+    async fn hold_until_beacon<T>(
+        ctx: &web::Data<AppState>,
+        get: impl AsyncFn(&AppState) -> Option<T>,
+    ) -> Result<T, CheckerError> {
+        loop {
+            if let Some(value) = get(ctx).await {
+                // we already have the randomness to continue the process
+                return Ok(value);
+            }
+            if !wait_beacon(ctx).await? {
+                return get(ctx).await.ok_or(CheckerError::RandomnessError);
+            }
+        }
+    }
+
+    /**
+     * Hold the DApp and take the next input, true when it brought new beacons
+     */
+    async fn wait_beacon(ctx: &web::Data<AppState>) -> Result<bool, CheckerError> {
+        if !ctx.hold().await {
             info!("When inspecting we does not call finish from /random endpoint.");
             return Err(CheckerError::AlreadyInspecting);
         }
-        // call finish to halt and wait the beacon
-        let rollup_input = match send_finish_and_retrieve_input("accept").await {
+        // call finish to halt and wait the beacon, the current input of the DApp is accepted
+        ctx.metrics.finish_roundtrips.inc();
+        let rollup_input = match send_finish_and_retrieve_input(&ctx.rollup_url, "accept").await {
             Ok(input) => input,
             Err(e) => {
                 error!("Error sending finish request: {}", e);
                 return Err(CheckerError::SendRollupAndRetrieveInputError);
             }
         };
+        ctx.fetched().await;
         match rollup_input.request_type.as_str() {
             "advance_state" => {
-                ctx.set_inspecting(false).await;
                 // Store the input in the buffer, so that it can be accessed from the /finish endpoint.
-                ctx.store_input(&rollup_input).await;
+                // A config update waits there too, behind the input holding the DApp.
+                if has_input_inside_input(&rollup_input) || has_drand_config(&rollup_input) {
+                    ctx.store_input(&rollup_input).await;
+                }
 
-                match get_drand_beacon(&rollup_input.data.payload) {
-                    Ok(beacon) => {
-                        info!("Is Drand!!! {:?}", beacon);
-                        ctx.keep_newest_beacon(beacon);
-                        let randomness = ctx.get_randomness_for_timestamp(query.timestamp);
-                        if let Some(randomness) = randomness {
-                            return Ok(HttpResponse::Ok().body(randomness));
-                        }
-                        Err(CheckerError::RandomnessError)
+                match keep_input_beacons(ctx, &rollup_input).await {
+                    Some(beacons) if !beacons.kept.is_empty() || !beacons.ignored.is_empty() => {
+                        info!("Is Drand!!! {:?}", beacons);
+                        report_beacon_only_input(ctx, &rollup_input, &beacons).await;
+                        Ok(!beacons.kept.is_empty())
+                    }
+                    Some(beacons) => {
+                        error!("Error getting randomness: {:?}", beacons.rejected);
+                        report_beacon_only_input(ctx, &rollup_input, &beacons).await;
+                        Err(CheckerError::SignatureErrorBeacon)
                     }
-                    Err(e) => {
-                        error!("Error getting randomness: {}", e);
+                    None => {
+                        error!("Error getting randomness: the input carries no beacon");
                         Err(CheckerError::SignatureErrorBeacon)
                     }
                 }
             }
             "inspect_state" => {
-                ctx.set_inspecting(true).await;
                 if is_querying_pending_beacon(&rollup_input).unwrap() {
-                    send_pending_beacon_report(&ctx).await;
+                    send_pending_beacon_report(ctx).await;
 
                     // This is a specific inspect, so we omit it from the DApp
                     Err(CheckerError::ByPassInspect)
+                } else if is_querying_status(&rollup_input).unwrap_or(false) {
+                    send_status_report(ctx).await;
+                    Err(CheckerError::ByPassInspect)
                 } else {
                     // Store the input in the buffer, so that it can be accessed from the /finish endpoint.
                     ctx.store_input(&rollup_input).await;
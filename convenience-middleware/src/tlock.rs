@@ -0,0 +1,217 @@
+use std::error::Error;
+
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+use sha2::{Digest, Sha256};
+
+use crate::{errors::CheckerError, models::structs::DrandScheme};
+
+/**
+ * Upper bound of a tlock message, the size of the hash used by the scheme
+ */
+pub const MAX_TLOCK_MESSAGE: usize = 32;
+
+/**
+ * Gt is an extension of degree 12 of the base field, whose elements have 48 bytes
+ */
+const GT_COEFFICIENTS: usize = 12;
+const FP_BYTES: usize = 48;
+
+/**
+ * U, V and W
+ */
+type CiphertextParts<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/**
+ * Decrypt a drand timelock ciphertext (Boneh-Franklin IBE with the Fujisaki-Okamoto transform).
+ * The beacon signature of a round is the IBE private key of that round,
+ * so a ciphertext encrypted to round N opens once the beacon of round N is known.
+ *
+ * The ciphertext is U || V || W, where U is a compressed point on the group of the public key
+ * and V and W have the length of the message.
+ */
+pub fn decrypt(
+    scheme: DrandScheme,
+    signature: &str,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    // the message of a chained round includes the previous signature, so tlock never encrypts to it
+    if scheme == DrandScheme::PedersenBlsChained {
+        return Err(CheckerError::TlockUnsupportedScheme {
+            scheme: scheme.as_str().to_string(),
+        }
+        .into());
+    }
+    let signature = hex::decode(signature.trim_start_matches("0x"))?;
+
+    match scheme {
+        // signatures on G2, so U is on G1
+        DrandScheme::PedersenBlsChained | DrandScheme::PedersenBlsUnchained => {
+            let (u, v, w) = split_ciphertext(ciphertext, 48)?;
+            let u = g1_from_compressed(u)?;
+            let signature = g2_from_compressed(&signature)?;
+            let r_gid = pairing(&u, &signature);
+            open(&r_gid, v, w, |r| G1Projective::generator() * r == u.into())
+        }
+        // signatures on G1, so U is on G2
+        DrandScheme::BlsUnchainedOnG1 | DrandScheme::BlsUnchainedG1Rfc9380 => {
+            let (u, v, w) = split_ciphertext(ciphertext, 96)?;
+            let u = g2_from_compressed(u)?;
+            let signature = g1_from_compressed(&signature)?;
+            let r_gid = pairing(&signature, &u);
+            open(&r_gid, v, w, |r| G2Projective::generator() * r == u.into())
+        }
+    }
+}
+
+fn open(
+    r_gid: &Gt,
+    v: &[u8],
+    w: &[u8],
+    is_u: impl Fn(Scalar) -> bool,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let sigma = xor(v, &gt_to_hash(r_gid, v.len())?);
+    let message = xor(w, &h4(&sigma, w.len()));
+
+    // U = rP proves the ciphertext was not tampered with
+    let r = h3(&sigma, &message)?;
+    if !is_u(r) {
+        return Err("Invalid tlock ciphertext, rP check failed".into());
+    }
+    Ok(message)
+}
+
+fn split_ciphertext(ciphertext: &[u8], u_len: usize) -> Result<CiphertextParts<'_>, String> {
+    let message_len = ciphertext.len().saturating_sub(u_len) / 2;
+    if message_len == 0
+        || message_len > MAX_TLOCK_MESSAGE
+        || ciphertext.len() != u_len + 2 * message_len
+    {
+        return Err(format!(
+            "Invalid tlock ciphertext length {}, expected {} bytes of U and up to {} bytes of V and W",
+            ciphertext.len(),
+            u_len,
+            MAX_TLOCK_MESSAGE
+        ));
+    }
+    let (u, rest) = ciphertext.split_at(u_len);
+    let (v, w) = rest.split_at(message_len);
+    Ok((u, v, w))
+}
+
+fn g1_from_compressed(bytes: &[u8]) -> Result<G1Affine, Box<dyn Error>> {
+    let bytes: &[u8; 48] = bytes.try_into()?;
+    Option::from(G1Affine::from_compressed(bytes)).ok_or_else(|| "Invalid G1 point".into())
+}
+
+fn g2_from_compressed(bytes: &[u8]) -> Result<G2Affine, Box<dyn Error>> {
+    let bytes: &[u8; 96] = bytes.try_into()?;
+    Option::from(G2Affine::from_compressed(bytes)).ok_or_else(|| "Invalid G2 point".into())
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+/**
+ * Serialize a Gt element like drand does: the 12 base field coefficients from the last to the first,
+ * each one big-endian. bls12_381 0.8 has no accessor for the coefficients of Gt and its Display
+ * is the only way to read them, so the text is checked strictly and the version is pinned.
+ */
+pub fn gt_to_bytes(gt: &Gt) -> Result<Vec<u8>, CheckerError> {
+    gt_bytes_from_display(&gt.to_string())
+}
+
+/**
+ * Read the coefficients of `Gt(c0 + (c1)*w)`, where each Fp6 is `c0 + (c1)*v + (c2)*v^2`,
+ * each Fp2 is `c0 + c1*u` and each coefficient is 0x followed by its 48 bytes in hex.
+ * The text is written back from the coefficients found, and must match.
+ */
+pub fn gt_bytes_from_display(display: &str) -> Result<Vec<u8>, CheckerError> {
+    let invalid = |cause: &str| CheckerError::TlockGtEncoding {
+        cause: cause.to_string(),
+    };
+
+    let mut coefficients = Vec::with_capacity(GT_COEFFICIENTS);
+    for field in display.split("0x").skip(1) {
+        let hex_len = field
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(field.len());
+        let coefficient = &field[..hex_len];
+        if coefficient.len() != 2 * FP_BYTES {
+            return Err(invalid("expected coefficients of 48 bytes"));
+        }
+        coefficients.push(coefficient);
+    }
+    let [a, b, c, d, e, f, g, h, i, j, k, l] = coefficients[..] else {
+        return Err(invalid("expected 12 coefficients"));
+    };
+
+    let fp6 = |c0: [&str; 2], c1: [&str; 2], c2: [&str; 2]| {
+        format!(
+            "0x{} + 0x{}*u + (0x{} + 0x{}*u)*v + (0x{} + 0x{}*u)*v^2",
+            c0[0], c0[1], c1[0], c1[1], c2[0], c2[1]
+        )
+    };
+    let expected = format!(
+        "Gt({} + ({})*w)",
+        fp6([a, b], [c, d], [e, f]),
+        fp6([g, h], [i, j], [k, l])
+    );
+    if display != expected {
+        return Err(invalid("expected Gt(c0 + (c1)*w)"));
+    }
+
+    coefficients
+        .iter()
+        .rev()
+        .map(|coefficient| hex::decode(coefficient).map_err(|e| invalid(&e.to_string())))
+        .collect::<Result<Vec<_>, _>>()
+        .map(|coefficients| coefficients.concat())
+}
+
+/**
+ * H2: SHA-256("IBE-H2" || rGid) truncated to the message length
+ */
+pub fn gt_to_hash(r_gid: &Gt, len: usize) -> Result<Vec<u8>, CheckerError> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"IBE-H2");
+    hasher.update(gt_to_bytes(r_gid)?);
+    Ok(hasher.finalize()[..len].to_vec())
+}
+
+/**
+ * H3: scalar from SHA-256(i || SHA-256("IBE-H3" || sigma || message)),
+ * trying i = 1, 2, ... until the masked big-endian hash is below the group order
+ */
+pub fn h3(sigma: &[u8], message: &[u8]) -> Result<Scalar, Box<dyn Error>> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"IBE-H3");
+    hasher.update(sigma);
+    hasher.update(message);
+    let buffer = hasher.finalize();
+
+    for i in 1..u16::MAX {
+        let mut hasher = Sha256::new();
+        hasher.update(i.to_le_bytes());
+        hasher.update(buffer);
+        let mut hashed: [u8; 32] = hasher.finalize().into();
+
+        // the order has 255 bits
+        hashed[0] >>= 1;
+        hashed.reverse();
+        if let Some(scalar) = Option::from(Scalar::from_bytes(&hashed)) {
+            return Ok(scalar);
+        }
+    }
+    Err("Cannot derive the tlock scalar".into())
+}
+
+/**
+ * H4: SHA-256("IBE-H4" || sigma) truncated to the message length
+ */
+pub fn h4(sigma: &[u8], len: usize) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"IBE-H4");
+    hasher.update(sigma);
+    hasher.finalize()[..len].to_vec()
+}
@@ -1,12 +1,26 @@
 pub mod util {
-    use std::{error::Error, path::Path};
+    use std::{
+        error::Error,
+        fs::{self, File, OpenOptions},
+        io::Write,
+        path::{Path, PathBuf},
+    };
 
     use dotenvy::var;
-    use log::info;
     use serde_json::Value;
-    use tokio::fs::read_to_string;
 
-    use crate::models::structs::DrandEnv;
+    use cartesi_rollup_metadata::Address;
+
+    use crate::models::structs::{
+        DerivationScheme, DrandEnv, DrandScheme, Journal, JournalDelta, ProvenanceMode,
+    };
+
+    /**
+     * Journal of the input buffer, kept next to drand.config.json
+     */
+    pub const JOURNAL_FILE_NAME: &str = "drand.journal.json";
+
+    pub const DRAND_CONFIG_PATH: &str = "drand.config.json";
 
     pub fn generate_payload_hex<T>(json: T) -> Result<String, Box<dyn Error>>
     where
@@ -27,56 +41,128 @@ pub mod util {
         }
     }
 
-    fn var_string_to_u64(str: &str) -> u64 {
-        let err_msg = format!("Var {} is not defined", str);
-        let value = var(str).expect(&err_msg);
-        let err_msg = format!("Var {} cannot parse", str);
-        value.parse::<u64>().expect(&err_msg)
+    fn var_to_u64(name: &str) -> Result<Option<u64>, Box<dyn Error>> {
+        match var(name) {
+            Ok(value) => Ok(Some(value.parse::<u64>()?)),
+            Err(_) => Ok(None),
+        }
     }
 
-    pub async fn write_env_to_json() -> Result<(), Box<dyn Error>> {
-        let path = Path::new("drand.config.json");
-
-        let drand_env = DrandEnv {
-            DRAND_PUBLIC_KEY: var("DRAND_PUBLIC_KEY").unwrap(),
-            DRAND_PERIOD: Some(var_string_to_u64("DRAND_PERIOD")),
-            DRAND_GENESIS_TIME: Some(var_string_to_u64("DRAND_GENESIS_TIME")),
-            DRAND_SAFE_SECONDS: Some(var_string_to_u64("DRAND_SAFE_SECONDS")),
-        };
-
-        let content = serde_json::to_string_pretty(&drand_env)?;
-        tokio::fs::write(path, content).await?;
-        Ok(())
+    pub fn drand_scheme_from_env() -> Result<DrandScheme, Box<dyn Error>> {
+        match var("DRAND_SCHEME") {
+            Ok(scheme) => Ok(scheme.parse::<DrandScheme>()?),
+            Err(_) => Ok(DrandScheme::default()),
+        }
     }
 
-    pub async fn load_env_from_memory(drand: DrandEnv) {
-        info!("DRAND_PUBLIC_KEY={}", &drand.DRAND_PUBLIC_KEY);
-        std::env::set_var("DRAND_PUBLIC_KEY", drand.DRAND_PUBLIC_KEY);
-        if let Some(period) = drand.DRAND_PERIOD {
-            std::env::set_var("DRAND_PERIOD", period.to_string());
+    pub fn provenance_mode_from_env() -> Result<ProvenanceMode, Box<dyn Error>> {
+        match var("DRAND_PROVENANCE") {
+            Ok(mode) => Ok(mode.parse::<ProvenanceMode>()?),
+            Err(_) => Ok(ProvenanceMode::default()),
         }
+    }
 
-        if let Some(genesis_time) = drand.DRAND_GENESIS_TIME {
-            std::env::set_var("DRAND_GENESIS_TIME", genesis_time.to_string());
+    pub fn derivation_scheme_from_env() -> Result<DerivationScheme, Box<dyn Error>> {
+        match var("DRAND_DERIVATION") {
+            Ok(scheme) => Ok(scheme.parse::<DerivationScheme>()?),
+            Err(_) => Ok(DerivationScheme::default()),
         }
+    }
 
-        if let Some(safe_seconds) = drand.DRAND_SAFE_SECONDS {
-            std::env::set_var("DRAND_SAFE_SECONDS", safe_seconds.to_string());
+    /**
+     * Drand values from the env, the scheduled rotations are kept as json in DRAND_ROTATIONS
+     */
+    pub fn drand_env_from_env() -> Result<DrandEnv, Box<dyn Error>> {
+        Ok(DrandEnv {
+            DRAND_PUBLIC_KEY: var("DRAND_PUBLIC_KEY").unwrap_or_default(),
+            DRAND_PERIOD: var_to_u64("DRAND_PERIOD")?,
+            DRAND_GENESIS_TIME: var_to_u64("DRAND_GENESIS_TIME")?,
+            DRAND_SAFE_SECONDS: var_to_u64("DRAND_SAFE_SECONDS")?,
+            DRAND_SCHEME: Some(drand_scheme_from_env()?),
+            DRAND_CHAIN_HASH: var("DRAND_CHAIN_HASH").ok(),
+            DRAND_EFFECTIVE_TIME: var_to_u64("DRAND_EFFECTIVE_TIME")?,
+            DRAND_ROTATIONS: match var("DRAND_ROTATIONS") {
+                Ok(rotations) => serde_json::from_str(&rotations)?,
+                Err(_) => vec![],
+            },
+        })
+    }
+
+    /**
+     * Address allowed to update the drand config through an advance input
+     */
+    pub fn config_owner_from_env() -> Result<Option<Address>, Box<dyn Error>> {
+        match var("DRAND_CONFIG_OWNER") {
+            Ok(owner) => Ok(Some(owner.parse::<Address>()?)),
+            Err(_) => Ok(None),
         }
     }
 
-    pub async fn load_env_from_json() -> Result<(), Box<dyn Error>> {
-        info!("Loading env from json");
+    pub async fn write_env_to_json(
+        path: &Path,
+        drand_env: &DrandEnv,
+    ) -> Result<(), Box<dyn Error>> {
+        let content = serde_json::to_string_pretty(drand_env)?;
+        tokio::fs::write(path, content).await?;
+        Ok(())
+    }
+
+    /**
+     * Deltas of the journal written since its last snapshot, one json per line
+     */
+    pub fn journal_log_path(path: &Path) -> PathBuf {
+        path.with_extension("log")
+    }
 
-        let path = Path::new("drand.config.json");
-        if !path.exists() {
-            return Err("File not found".into());
-        }
-        let content = read_to_string(path).await?;
-        let json = serde_json::from_str::<DrandEnv>(&content)?;
+    /**
+     * Replace the journal snapshot atomically, so a crash leaves either the old or the new state,
+     * then empty its log. The deltas left by a crash in between are older than the snapshot.
+     */
+    pub fn write_journal(path: &Path, journal: &Journal) -> Result<(), Box<dyn Error>> {
+        let tmp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_vec(journal)?;
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        File::create(journal_log_path(path))?.sync_all()?;
+        Ok(())
+    }
 
-        load_env_from_memory(json).await;
+    pub fn append_journal(path: &Path, delta: &JournalDelta) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_vec(delta)?;
+        line.push(b'\n');
 
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(journal_log_path(path))?;
+        file.write_all(&line)?;
+        file.sync_data()?;
         Ok(())
     }
+
+    /**
+     * Deltas of the journal log in order, a line cut by a crash ends the log
+     */
+    pub fn read_journal_log(path: &Path) -> Result<Vec<JournalDelta>, Box<dyn Error>> {
+        let path = journal_log_path(path);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(content
+            .lines()
+            .map_while(|line| serde_json::from_str::<JournalDelta>(line).ok())
+            .collect())
+    }
+
+    pub fn read_journal(path: &Path) -> Result<Option<Journal>, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str::<Journal>(&content)?))
+    }
 }
@@ -16,7 +16,6 @@ pub mod rollup {
                 decode_payload, generate_report, get_address_metadata_from_root, get_path_player,
                 get_path_player_name, load_json, write_json,
             },
-            pubkey::{call_update_key, DrandEnv},
             random::retrieve_seed,
         },
     };
@@ -114,7 +113,7 @@ pub mod rollup {
     }
 
     pub async fn send_report(report: Value) -> Result<&'static str, Box<dyn Error>> {
-        let server_addr = var("ROLLUP_HTTP_SERVER_URL")?;
+        let server_addr = var("MIDDLEWARE_HTTP_SERVER_URL")?;
         let client = hyper::Client::new();
         let req = hyper::Request::builder()
             .method(hyper::Method::POST)
@@ -127,7 +126,7 @@ pub mod rollup {
     }
 
     pub async fn send_notice(notice: Value) -> Result<(), Box<dyn Error>> {
-        let server_addr = var("ROLLUP_HTTP_SERVER_URL")?;
+        let server_addr = var("MIDDLEWARE_HTTP_SERVER_URL")?;
         let client = hyper::Client::new();
         let req = hyper::Request::builder()
             .method(hyper::Method::POST)
@@ -191,48 +190,6 @@ pub mod rollup {
         info!("Action: {:}", action.as_deref().unwrap_or("None"));
 
         match action.as_deref() {
-            Some("update_drand") => {
-                let input = payload.get("input").ok_or("Invalid field input")?;
-
-                let metadata = get_address_metadata_from_root(root).ok_or("Invalid address")?;
-                let address_owner = metadata.address.trim_start_matches("0x").to_lowercase();
-
-                let address_owner_game =
-                    var("ADDRESS_OWNER_GAME").or(Err("Address owner game not defined"))?;
-
-                let address_owner_game = address_owner_game.trim_start_matches("0x").to_lowercase();
-
-                if address_owner != address_owner_game {
-                    return Err("Invalid owner".into());
-                }
-
-                // Parsing JSON
-                let public_key = input
-                    .get("public_key")
-                    .ok_or("Invalid field public_key")?
-                    .as_str()
-                    .ok_or("Invalid public_key")?;
-
-                let period = input.get("period").map(|v| v.as_u64()).unwrap_or(None);
-
-                let genesis_time = input
-                    .get("genesis_time")
-                    .map(|v| v.as_u64())
-                    .unwrap_or(None);
-
-                let safe_seconds = input
-                    .get("safe_seconds")
-                    .map(|v| v.as_u64())
-                    .unwrap_or(None);
-
-                let request_env = DrandEnv::new(public_key, period, genesis_time, safe_seconds);
-
-                let response = call_update_key(&request_env).await;
-
-                if response.is_err() {
-                    return Err("Could not update drand config".into());
-                }
-            }
             Some("new_player") => {
                 let input = payload.get("input").ok_or("Invalid field input")?;
                 let player_name = check_fields_create_player(input)?;
@@ -240,8 +197,8 @@ pub mod rollup {
                 let encoded_name = bs58::encode(&player_name).into_string();
 
                 let metadata = get_address_metadata_from_root(root).ok_or("Invalid address")?;
-                let address_owner = metadata.address.trim_start_matches("0x");
-                let address_encoded = bs58::encode(address_owner).into_string();
+                let address_owner = format!("{:x}", metadata.msg_sender);
+                let address_encoded = bs58::encode(&address_owner).into_string();
 
                 // Add player to manager
                 let player = Player::new(address_encoded.clone(), player_name.to_string());
@@ -260,7 +217,7 @@ pub mod rollup {
                         .or(Err("Could not write address"))?;
 
                     let player_path = get_path_player_name(&encoded_name);
-                    let player = json!({ "name": encoded_name, "address": metadata.address });
+                    let player = json!({ "name": encoded_name, "address": format!("{:#x}", metadata.msg_sender) });
                     write_json(&player_path, &player)
                         .await
                         .or(Err("Could not write player"))?;
@@ -281,8 +238,8 @@ pub mod rollup {
 
                 // Address
                 let metadata = get_address_metadata_from_root(root).ok_or("Invalid address")?;
-                let address_owner = metadata.address.trim_start_matches("0x");
-                let address_encoded = bs58::encode(address_owner).into_string();
+                let address_owner = format!("{:x}", metadata.msg_sender);
+                let address_encoded = bs58::encode(&address_owner).into_string();
 
                 // load to memory if not exists
                 if write_hd_mode {
@@ -312,7 +269,7 @@ pub mod rollup {
                     .as_str()
                     .ok_or("Invalid address")?;
                 let address_owner = address.trim_start_matches("0x");
-                let address_encoded = bs58::encode(address_owner).into_string();
+                let address_encoded = bs58::encode(&address_owner).into_string();
 
                 // load to memory if not exists
                 if write_hd_mode {
@@ -457,8 +414,8 @@ pub mod rollup {
             Some("hit") => {
                 // Address
                 let metadata = get_address_metadata_from_root(root).ok_or("Invalid address")?;
-                let address_owner = metadata.address.trim_start_matches("0x");
-                let address_encoded = bs58::encode(address_owner).into_string();
+                let address_owner = format!("{:x}", metadata.msg_sender);
+                let address_encoded = bs58::encode(&address_owner).into_string();
                 let timestamp = metadata.timestamp;
 
                 // Table ID
@@ -490,8 +447,8 @@ pub mod rollup {
                     .ok_or("Invalid game_id")?;
 
                 let metadata = get_address_metadata_from_root(root).ok_or("Invalid address")?;
-                let address_owner = metadata.address.trim_start_matches("0x");
-                let address_encoded = bs58::encode(address_owner).into_string();
+                let address_owner = format!("{:x}", metadata.msg_sender);
+                let address_encoded = bs58::encode(&address_owner).into_string();
 
                 let mut manager = manager.lock().await;
                 let table = manager.get_table_mut(game_id)?;
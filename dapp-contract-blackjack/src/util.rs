@@ -1,7 +1,3 @@
-pub struct Metadata {
-    pub address: String,
-    pub timestamp: u64,
-}
 pub mod random {
     use std::{error::Error, ops::Range};
 
@@ -100,13 +96,14 @@ pub mod json {
     use std::path::PathBuf;
 
     use log::info;
+    use serde::Deserialize;
     use serde_json::{json, Value};
     use tokio::{
         fs::{read_to_string, File},
         io::{self, AsyncWriteExt},
     };
 
-    use super::Metadata;
+    use cartesi_rollup_metadata::Metadata;
 
     pub fn decode_payload<T>(payload: &str) -> Result<T, Box<dyn std::error::Error>>
     where
@@ -168,81 +165,8 @@ pub mod json {
     }
 
     pub fn get_address_metadata_from_root(root: &Value) -> Option<Metadata> {
-        let root = root.as_object()?;
-        let root = root.get("data")?.as_object()?;
-        let metadata = root.get("metadata")?.as_object()?;
-
-        let address = metadata.get("msg_sender")?.as_str()?;
-        let timestamp = metadata.get("timestamp")?.as_u64()?;
-        // let input_index = metadata.get("input_index")?.as_u64()?;
-
-        Some(Metadata {
-            address: address.to_owned(),
-            timestamp,
-            // input_index,
-        })
-    }
-}
-
-pub mod pubkey {
-    use std::error::Error;
-
-    use dotenvy::var;
-    use hyper::{Body, Client, Method, Request};
-    use log::{error, info};
-
-    #[derive(serde::Deserialize, serde::Serialize)]
-    #[allow(non_snake_case)]
-    pub struct DrandEnv {
-        pub DRAND_PUBLIC_KEY: String,
-        pub DRAND_PERIOD: Option<u64>,
-        pub DRAND_GENESIS_TIME: Option<u64>,
-        pub DRAND_SAFE_SECONDS: Option<u64>,
-    }
-
-    impl DrandEnv {
-        pub fn new(
-            pubkey: &str,
-            period: Option<u64>,
-            genesis_time: Option<u64>,
-            safe_seconds: Option<u64>,
-        ) -> Self {
-            Self {
-                DRAND_PUBLIC_KEY: pubkey.to_owned(),
-                DRAND_PERIOD: period,
-                DRAND_GENESIS_TIME: genesis_time,
-                DRAND_SAFE_SECONDS: safe_seconds,
-            }
-        }
-    }
-
-    pub async fn call_update_key(drand_env: &DrandEnv) -> Result<(), Box<dyn Error>> {
-        let body = serde_json::to_string(drand_env)?;
-
-        let client = Client::new();
-        let server_addr = var("MIDDLEWARE_HTTP_SERVER_URL")?;
-        let server_addr = server_addr.trim_end_matches('/');
-
-        let uri = format!("{}/update_drand_config", &server_addr);
-
-        info!("Calling update key at {:}", &uri);
-
-        let request = Request::builder()
-            .method(Method::PUT)
-            .uri(&uri)
-            .header("Content-Type", "application/json")
-            .body(Body::from(body))?;
-
-        let response = client.request(request).await?;
-
-        if response.status().is_success() {
-            info!("Update key success");
-            Ok(())
-        } else {
-            let msg = "Update key failed";
-            error!("{}", msg);
-            Err(msg.into())
-        }
+        let metadata = root.get("data")?.get("metadata")?;
+        Metadata::deserialize(metadata).ok()
     }
 }
 
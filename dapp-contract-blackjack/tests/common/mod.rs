@@ -18,44 +18,6 @@ pub mod prelude {
     static BIND_SERVER: Once = Once::new();
     static SERVER_POOL: ServerPool = ServerPool::new(1);
 
-    pub async fn setup_change_key() -> impl Drop {
-        add_expectation(
-            all_of![request::method_path(
-                hyper::Method::PUT.as_str(),
-                "/update_drand_config"
-            ),],
-            status_code(204),
-        )
-        .await
-    }
-
-    pub async fn setup_dont_change_key() -> impl Drop {
-        let server = SERVER_POOL.get_server();
-
-        server.expect(
-            Expectation::matching(request::method_path(
-                hyper::Method::PUT.as_str(),
-                "/update_drand_config",
-            ))
-            .times(0)
-            .respond_with(status_code(500)),
-        );
-
-        let url = server.url_str("");
-
-        BIND_SERVER.call_once(|| {
-            set_var("MIDDLEWARE_HTTP_SERVER_URL", &url);
-            set_var("ROLLUP_HTTP_SERVER_URL", &url);
-        });
-
-        assert!(var("MIDDLEWARE_HTTP_SERVER_URL").is_ok());
-        assert!(var("ROLLUP_HTTP_SERVER_URL").is_ok());
-
-        println!("Server listening on {}", &url);
-
-        server
-    }
-
     pub async fn setup_hit_random() -> impl Drop {
         let message = generate_message(Value::from("blackjack"));
 
@@ -85,11 +47,9 @@ pub mod prelude {
 
         BIND_SERVER.call_once(|| {
             set_var("MIDDLEWARE_HTTP_SERVER_URL", &url);
-            set_var("ROLLUP_HTTP_SERVER_URL", &url);
         });
 
         assert!(var("MIDDLEWARE_HTTP_SERVER_URL").is_ok());
-        assert!(var("ROLLUP_HTTP_SERVER_URL").is_ok());
 
         println!("Server listening on {}", &url);
 
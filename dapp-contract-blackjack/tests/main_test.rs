@@ -7,7 +7,7 @@ use imports::*;
 #[cfg(test)]
 mod contract_blackjack_tests {
     use crate::{
-        common::prelude::{setup_change_key, setup_dont_change_key, setup_hit_random},
+        common::prelude::setup_hit_random,
         models::{game::prelude::Manager, player::prelude::Player},
         rollups::rollup::handle_request_action,
         util::{env::check_if_dotenv_is_loaded, json::decode_payload, random::retrieve_seed},
@@ -28,7 +28,7 @@ mod contract_blackjack_tests {
         let payload = format!("0x{}", payload);
 
         let metadata = json!({
-            "msg_sender": "0xdeadbeef",
+            "msg_sender": "0x00000000000000000000000000000000deadbeef",
             "epoch_index": 0u64,
             "input_index": 0u64,
             "block_number": 123u64,
@@ -64,7 +64,7 @@ mod contract_blackjack_tests {
         assert!(result.is_ok(), "Result is not ok: {:}", result.unwrap_err());
         assert!(result.unwrap().is_some(), "Result is not some");
 
-        let address = "0xdeadbeef";
+        let address = "0x00000000000000000000000000000000deadbeef";
         let address = bs58::encode(address[2..].to_string()).into_string();
 
         println!("Address {}", address);
@@ -360,7 +360,7 @@ mod contract_blackjack_tests {
         manager.player_join(&game_id, player).unwrap();
 
         // Second player
-        let id = String::from("0xdeadbeef");
+        let id = String::from("0x00000000000000000000000000000000deadbeef");
         let id = bs58::encode(id[2..].to_string()).into_string();
         let player = Player::new(id, "Bob".to_string());
         let player = Arc::from(player);
@@ -566,62 +566,4 @@ mod contract_blackjack_tests {
 
         assert!(response.is_some(), "Missing return");
     }
-
-    #[tokio::test]
-    async fn should_change_key() {
-        check_if_dotenv_is_loaded!();
-        let _server = setup_change_key().await;
-
-        let manager = Arc::new(Mutex::new(Manager::default()));
-
-        let payload = json!({
-            "input": {
-                "action": "update_drand",
-                "public_key": "0xdeadbeef"
-            }
-        });
-
-        // Generate complete message with payload
-        let data = factory_message(payload);
-
-        let msg_sender = data["data"]["metadata"]["msg_sender"].as_str().unwrap();
-
-        std::env::set_var("ADDRESS_OWNER_GAME", msg_sender);
-
-        let response = handle_request_action(&data, manager, false).await;
-
-        assert!(
-            response.is_ok(),
-            "Response is not ok {}",
-            response.unwrap_err()
-        );
-    }
-
-    #[tokio::test]
-    async fn shouldnt_change_key() {
-        check_if_dotenv_is_loaded!();
-        let _server = setup_dont_change_key().await;
-
-        let manager = Arc::new(Mutex::new(Manager::default()));
-
-        let payload = json!({
-            "input": {
-                "action": "update_drand",
-                "public_key": "0xdeadbeef"
-            }
-        });
-
-        // Generate complete message with payload
-        let data = factory_message(payload);
-
-        std::env::set_var("ADDRESS_OWNER_GAME", "0xbobdummy");
-
-        let response = handle_request_action(&data, manager, false).await;
-
-        assert!(
-            response.is_err(),
-            "Response is not ok {}",
-            response.unwrap_err()
-        );
-    }
 }
@@ -0,0 +1,96 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+
+/**
+ * 20 bytes Ethereum address, displayed with the EIP-55 checksum
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /**
+     * EIP-55 mixed case hex, 0x prefixed
+     */
+    pub fn to_checksum(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = Keccak256::digest(lower.as_bytes());
+
+        let checksum: String = lower
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+
+        format!("0x{}", checksum)
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    /**
+     * Accepts all lower or all upper case hex, mixed case must match the checksum
+     */
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        let mut bytes = [0u8; 20];
+        hex::decode_to_slice(digits, &mut bytes)
+            .map_err(|e| format!("Invalid address {}: {}", s, e))?;
+
+        let address = Address(bytes);
+        let is_mixed_case = digits.chars().any(|c| c.is_ascii_lowercase())
+            && digits.chars().any(|c| c.is_ascii_uppercase());
+        if is_mixed_case && address.to_checksum()[2..] != *digits {
+            return Err(format!("Invalid address checksum {}", s));
+        }
+
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl fmt::LowerHex for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_checksum())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,10 @@
+/**
+ * Typed metadata of the Cartesi rollup inputs, shared by the middleware and the DApps
+ */
+mod address;
+mod metadata;
+pub mod u256;
+
+pub use address::Address;
+pub use ethnum::U256;
+pub use metadata::Metadata;
@@ -0,0 +1,148 @@
+use ethnum::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::address::Address;
+
+/**
+ * Metadata of an advance input, as sent by the rollup server
+ *
+ * {"block_number":241,"epoch_index":0,"input_index":0,"msg_sender":"0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266","timestamp":1689949250}
+ *
+ * Newer node versions drop epoch_index, rename timestamp to block_timestamp
+ * and add chain_id, app_contract and prev_randao
+ */
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    #[serde(with = "crate::u256")]
+    pub block_number: U256,
+    #[serde(
+        default,
+        with = "crate::u256::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub epoch_index: Option<U256>,
+    #[serde(with = "crate::u256")]
+    pub input_index: U256,
+    pub msg_sender: Address,
+    #[serde(alias = "block_timestamp")]
+    pub timestamp: u64,
+    #[serde(
+        default,
+        with = "crate::u256::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub chain_id: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_contract: Option<Address>,
+    #[serde(
+        default,
+        with = "crate::u256::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub prev_randao: Option<U256>,
+}
+
+impl Metadata {
+    /**
+     * Input index as u64, saturating when it does not fit
+     */
+    pub fn input_index_u64(&self) -> u64 {
+        u64::try_from(self.input_index).unwrap_or(u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{Address, Metadata, U256};
+
+    #[test]
+    fn test_decode_legacy_metadata() {
+        let value = json!({"block_number":241,"epoch_index":0,"input_index":3,"msg_sender":"0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266","timestamp":1689949250});
+        let metadata: Metadata = serde_json::from_value(value.clone()).unwrap();
+
+        assert_eq!(metadata.block_number, U256::new(241));
+        assert_eq!(metadata.epoch_index, Some(U256::ZERO));
+        assert_eq!(metadata.input_index_u64(), 3);
+        assert_eq!(metadata.timestamp, 1689949250);
+        assert_eq!(metadata.chain_id, None);
+
+        // numbers keep their JSON type and the address gets its checksum
+        let encoded = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(encoded["block_number"], json!(241));
+        assert_eq!(
+            encoded["msg_sender"],
+            json!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+        );
+        assert_eq!(
+            serde_json::from_value::<Metadata>(encoded).unwrap(),
+            metadata
+        );
+    }
+
+    #[test]
+    fn test_decode_newer_metadata() {
+        let value = json!({
+            "chain_id": 31337,
+            "app_contract": "0xab7528bb862fb57e8a2bcd567a2e929a0be56a5e",
+            "msg_sender": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "block_number": "0x10",
+            "block_timestamp": 1720000000,
+            "prev_randao": "0x3ba3e4f1bd2cbbd2d0a8a0e0a6bd7c4a12a2b4a5f5c6d7e8f90a1b2c3d4e5f60",
+            "input_index": "340282366920938463463374607431768211456"
+        });
+        let metadata: Metadata = serde_json::from_value(value).unwrap();
+
+        assert_eq!(metadata.chain_id, Some(U256::new(31337)));
+        assert_eq!(metadata.block_number, U256::new(16));
+        assert_eq!(metadata.timestamp, 1720000000);
+        assert_eq!(metadata.epoch_index, None);
+        assert_eq!(metadata.input_index, U256::from_words(1, 0));
+        assert_eq!(metadata.input_index_u64(), u64::MAX);
+        assert_eq!(
+            format!("{:#x}", metadata.prev_randao.unwrap()),
+            "0x3ba3e4f1bd2cbbd2d0a8a0e0a6bd7c4a12a2b4a5f5c6d7e8f90a1b2c3d4e5f60"
+        );
+        assert_eq!(
+            metadata.app_contract.unwrap().to_string(),
+            "0xab7528bb862fB57E8A2BCd567a2e929a0Be56a5e"
+        );
+
+        let encoded = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(
+            encoded["input_index"],
+            json!("0x100000000000000000000000000000000")
+        );
+        assert_eq!(
+            serde_json::from_value::<Metadata>(encoded).unwrap(),
+            metadata
+        );
+    }
+
+    #[test]
+    fn test_address_checksum() {
+        let address: Address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            address.to_string(),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert_eq!(
+            format!("{:#x}", address),
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+        );
+
+        assert!("0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"
+            .parse::<Address>()
+            .is_ok());
+        assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            .parse::<Address>()
+            .is_ok());
+        assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+            .parse::<Address>()
+            .is_err());
+        assert!("0x5aaeb6".parse::<Address>().is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use ethnum::U256;
+use serde::{Deserializer, Serializer};
+
+/**
+ * Serde of the U256 fields.
+ * Reads numbers, decimal strings and 0x prefixed hex strings.
+ * Writes a plain number while it fits in u64, so older DApps keep reading them with as_u64,
+ * and a 0x prefixed hex string otherwise.
+ */
+pub fn serialize<S>(value: &U256, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match u64::try_from(*value) {
+        Ok(value) => serializer.serialize_u64(value),
+        Err(_) => serializer.serialize_str(&format!("{:#x}", value)),
+    }
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<U256, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    ethnum::serde::permissive::deserialize(deserializer)
+}
+
+/**
+ * Same as the parent module for the optional fields of newer rollup formats
+ */
+pub mod option {
+    use ethnum::U256;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &Option<U256>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => super::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<U256>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super")] U256);
+
+        let value = Option::<Wrapper>::deserialize(deserializer)?;
+        Ok(value.map(|Wrapper(value)| value))
+    }
+}